@@ -0,0 +1,56 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use nine_patch::bench;
+use std::hint::black_box;
+
+// The phases of rendering `docs/bubble.9.png`, timed one by one. Run with
+// `cargo bench --features bench --bench phases`.
+
+// (name, width, height) of the rendered targets
+const TARGETS: [(&str, u32, u32); 3] = [
+    ("small", 600, 400),
+    ("medium", 1200, 800),
+    // A2 at 150 dpi, within the default output limits
+    ("poster", 3508, 2480),
+];
+
+fn phases(c: &mut Criterion) {
+    let image_bytes = std::fs::read(concat!(env!("CARGO_MANIFEST_DIR"), "/docs/bubble.9.png")).unwrap();
+
+    let rgba = bench::decode_image(&image_bytes).unwrap();
+    let dynamic = image::load_from_memory(&image_bytes).unwrap();
+    let borders = bench::parse_nine_patch_borders(&rgba).unwrap();
+    let content = bench::extract_content(&rgba);
+
+    // The source image goes through these once, whatever the target size.
+    // Loading includes the conversion to RGBA, which is also timed on its own.
+    c.bench_function("load", |b| b.iter(|| bench::decode_image(black_box(&image_bytes)).unwrap()));
+    c.bench_function("to_rgba8", |b| b.iter(|| black_box(&dynamic).to_rgba8()));
+    c.bench_function("parse_nine_patch_borders", |b| {
+        b.iter(|| bench::parse_nine_patch_borders(black_box(&rgba)).unwrap())
+    });
+    c.bench_function("extract_content", |b| b.iter(|| bench::extract_content(black_box(&rgba))));
+
+    let mut scale = c.benchmark_group("scale_nine_patch");
+    for (name, width, height) in TARGETS {
+        scale.bench_with_input(BenchmarkId::from_parameter(name), &(width, height), |b, &(width, height)| {
+            b.iter(|| bench::scale_nine_patch(black_box(&content), &borders, width, height).unwrap())
+        });
+    }
+    scale.finish();
+
+    let mut encode = c.benchmark_group("encode_png");
+    for (name, width, height) in TARGETS {
+        let scaled = bench::scale_nine_patch(&content, &borders, width, height).unwrap();
+        encode.bench_with_input(BenchmarkId::from_parameter(name), &scaled, |b, scaled| {
+            b.iter(|| bench::encode_png(black_box(scaled)).unwrap())
+        });
+    }
+    encode.finish();
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(20);
+    targets = phases
+}
+criterion_main!(benches);
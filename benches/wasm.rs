@@ -0,0 +1,107 @@
+use ciborium::cbor;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use std::hint::black_box;
+use wasmi::{Caller, Engine, Extern, Func, Instance, Linker, Memory, Module, Store, Val};
+
+// Whole `nine_patch` calls, natively and through the release wasm build run by
+// wasmi, the runtime Typst uses. Build the plugin first with
+// `cargo build --target wasm32-unknown-unknown --release`.
+
+const WASM: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/target/wasm32-unknown-unknown/release/nine_patch.wasm");
+
+// (name, width, height) of the rendered targets, as in `benches/phases.rs`
+const TARGETS: [(&str, u32, u32); 3] = [("small", 600, 400), ("medium", 1200, 800), ("poster", 3508, 2480)];
+
+// Arguments going into the plugin and the result coming back, passed through
+// the host functions of the wasm-minimal-protocol
+#[derive(Default)]
+struct Host {
+    args: Vec<u8>,
+    result: Vec<u8>,
+}
+
+struct Plugin {
+    store: Store<Host>,
+    instance: Instance,
+}
+
+fn memory(caller: &Caller<Host>) -> Memory {
+    caller.get_export("memory").and_then(Extern::into_memory).expect("Plugin should export its memory")
+}
+
+impl Plugin {
+    fn load() -> Plugin {
+        let wasm = std::fs::read(WASM).unwrap_or_else(|e| {
+            panic!("Failed to read {}: {}, build it with `cargo build --target wasm32-unknown-unknown --release`", WASM, e)
+        });
+
+        let engine = Engine::default();
+        let module = Module::new(&engine, &wasm).unwrap();
+        let mut store = Store::new(&engine, Host::default());
+        let mut linker = Linker::new(&engine);
+
+        linker
+            .func_wrap("typst_env", "wasm_minimal_protocol_write_args_to_buffer", |mut caller: Caller<Host>, ptr: i32| {
+                let args = std::mem::take(&mut caller.data_mut().args);
+                memory(&caller).write(&mut caller, ptr as usize, &args).unwrap();
+                caller.data_mut().args = args;
+            })
+            .unwrap();
+        linker
+            .func_wrap("typst_env", "wasm_minimal_protocol_send_result_to_host", |mut caller: Caller<Host>, ptr: i32, len: i32| {
+                let mut result = vec![0; len as usize];
+                memory(&caller).read(&caller, ptr as usize, &mut result).unwrap();
+                caller.data_mut().result = result;
+            })
+            .unwrap();
+
+        let instance = linker.instantiate_and_start(&mut store, &module).unwrap();
+        Plugin { store, instance }
+    }
+
+    // Call a plugin function as Typst does: the arguments are written into the
+    // plugin's memory one after another and only their lengths are passed
+    fn call(&mut self, name: &str, args: &[&[u8]]) -> Vec<u8> {
+        let func: Func = self.instance.get_func(&self.store, name).unwrap();
+        let lengths: Vec<Val> = args.iter().map(|arg| Val::I32(arg.len() as i32)).collect();
+        self.store.data_mut().args = args.concat();
+
+        let mut code = [Val::I32(0)];
+        func.call(&mut self.store, &lengths, &mut code).unwrap();
+        assert_eq!(code[0].i32(), Some(0), "{} returned an error", name);
+
+        std::mem::take(&mut self.store.data_mut().result)
+    }
+}
+
+fn nine_patch(c: &mut Criterion) {
+    let image_bytes = std::fs::read(concat!(env!("CARGO_MANIFEST_DIR"), "/docs/bubble.9.png")).unwrap();
+
+    // Without caches every call renders from scratch
+    let mut no_cache = Vec::new();
+    ciborium::into_writer(&cbor!({ "asset-bytes" => 0, "render-bytes" => 0 }).unwrap(), &mut no_cache).unwrap();
+    nine_patch::configure_cache(&no_cache);
+
+    let mut plugin = Plugin::load();
+    plugin.call("configure_cache", &[&no_cache]);
+
+    let mut group = c.benchmark_group("nine_patch");
+    for (name, width, height) in TARGETS {
+        let (width, height) = (width.to_le_bytes(), height.to_le_bytes());
+
+        group.bench_function(BenchmarkId::new("native", name), |b| {
+            b.iter(|| nine_patch::nine_patch(black_box(&image_bytes), &width, &height))
+        });
+        group.bench_function(BenchmarkId::new("wasm", name), |b| {
+            b.iter(|| plugin.call("nine_patch", &[black_box(&image_bytes), &width, &height]))
+        });
+    }
+    group.finish();
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(10);
+    targets = nine_patch
+}
+criterion_main!(benches);
@@ -0,0 +1,29 @@
+#![no_main]
+
+use ciborium::cbor;
+use libfuzzer_sys::fuzz_target;
+
+// Input: 4 bytes width, 4 bytes height, 1 byte frame selector length, the CBOR
+// selector, 1 byte options length, the CBOR options, then the animation
+fuzz_target!(|data: &[u8]| {
+    // Small limits keep runs fast, and setting them clears the caches so that
+    // every input is rendered from scratch
+    let mut limits = Vec::new();
+    ciborium::into_writer(&cbor!({ "max-input-pixels" => 1 << 20, "max-output-pixels" => 1 << 20 }).unwrap(), &mut limits).unwrap();
+    nine_patch::configure_limits(&limits);
+
+    if data.len() < 10 {
+        return;
+    }
+    let (width, rest) = data.split_at(4);
+    let (height, rest) = rest.split_at(4);
+    let (frame, rest) = rest[1..].split_at((rest[0] as usize).min(rest.len() - 1));
+    if rest.is_empty() {
+        return;
+    }
+    let (options, image) = rest[1..].split_at((rest[0] as usize).min(rest.len() - 1));
+
+    nine_patch::animation_info(image);
+    nine_patch::nine_patch_animated(image, width, height, options);
+    nine_patch::nine_patch_frame(image, width, height, frame, options);
+});
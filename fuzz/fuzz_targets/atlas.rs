@@ -0,0 +1,13 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+// Input: 1 byte name length, the region name, then the atlas text
+fuzz_target!(|data: &[u8]| {
+    if data.is_empty() {
+        return;
+    }
+    let (name, atlas) = data[1..].split_at((data[0] as usize).min(data.len() - 1));
+
+    nine_patch::atlas_region(atlas, name);
+});
@@ -0,0 +1,20 @@
+#![no_main]
+
+use ciborium::cbor;
+use libfuzzer_sys::fuzz_target;
+
+// Input: 1 byte requests length, the CBOR requests, then the image
+fuzz_target!(|data: &[u8]| {
+    // Small limits keep runs fast, and setting them clears the caches so that
+    // every input is rendered from scratch
+    let mut limits = Vec::new();
+    ciborium::into_writer(&cbor!({ "max-input-pixels" => 1 << 20, "max-output-pixels" => 1 << 20 }).unwrap(), &mut limits).unwrap();
+    nine_patch::configure_limits(&limits);
+
+    if data.is_empty() {
+        return;
+    }
+    let (requests, image) = data[1..].split_at((data[0] as usize).min(data.len() - 1));
+
+    nine_patch::nine_patch_batch(image, requests);
+});
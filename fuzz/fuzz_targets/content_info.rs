@@ -0,0 +1,12 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+// Input: the image
+fuzz_target!(|data: &[u8]| {
+    // Setting the limits clears the caches, so that every input is parsed
+    // from scratch
+    nine_patch::configure_limits(&[]);
+
+    nine_patch::nine_patch_content_info(data);
+});
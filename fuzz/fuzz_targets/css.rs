@@ -0,0 +1,11 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+// Only the size of the image is read, so one image serves every input
+const IMAGE: &[u8] = include_bytes!("../../docs/bubble.9.png");
+
+// Input: the CSS text
+fuzz_target!(|data: &[u8]| {
+    nine_patch::css_border_image(IMAGE, data);
+});
@@ -0,0 +1,14 @@
+#![no_main]
+
+use ciborium::cbor;
+use libfuzzer_sys::fuzz_target;
+
+// Input: the CBOR frame parameters
+fuzz_target!(|data: &[u8]| {
+    // Small limits keep runs fast, and setting them clears the caches
+    let mut limits = Vec::new();
+    ciborium::into_writer(&cbor!({ "max-input-pixels" => 1 << 20, "max-output-pixels" => 1 << 20 }).unwrap(), &mut limits).unwrap();
+    nine_patch::configure_limits(&limits);
+
+    nine_patch::generate_nine_patch(data);
+});
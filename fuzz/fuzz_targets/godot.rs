@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+// Input: the resource text
+fuzz_target!(|data: &[u8]| {
+    nine_patch::godot_style_box(data);
+});
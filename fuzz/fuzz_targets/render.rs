@@ -0,0 +1,27 @@
+#![no_main]
+
+use ciborium::cbor;
+use libfuzzer_sys::fuzz_target;
+
+// Input: 4 bytes width, 4 bytes height, 1 byte options length, the CBOR
+// options, then the image
+fuzz_target!(|data: &[u8]| {
+    // Small limits keep runs fast, and setting them clears the caches so that
+    // every input is rendered from scratch
+    let mut limits = Vec::new();
+    ciborium::into_writer(&cbor!({ "max-input-pixels" => 1 << 20, "max-output-pixels" => 1 << 20 }).unwrap(), &mut limits).unwrap();
+    nine_patch::configure_limits(&limits);
+
+    if data.len() < 9 {
+        return;
+    }
+    let (width, rest) = data.split_at(4);
+    let (height, rest) = rest.split_at(4);
+    let (options, image) = rest[1..].split_at((rest[0] as usize).min(rest.len() - 1));
+
+    if options.is_empty() {
+        nine_patch::nine_patch(image, width, height);
+    } else {
+        nine_patch::nine_patch_with_options(image, width, height, options);
+    }
+});
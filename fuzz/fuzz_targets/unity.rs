@@ -0,0 +1,16 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+// Only the size of the image is read, so one image serves every input
+const IMAGE: &[u8] = include_bytes!("../../docs/bubble.9.png");
+
+// Input: 1 byte name length, the sprite name, then the meta file
+fuzz_target!(|data: &[u8]| {
+    if data.is_empty() {
+        return;
+    }
+    let (name, meta) = data[1..].split_at((data[0] as usize).min(data.len() - 1));
+
+    nine_patch::unity_sprite(IMAGE, meta, name);
+});
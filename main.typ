@@ -1,35 +1,447 @@
 // Load the nine-patch WASM plugin
 #let nine-patch-plugin = plugin("./nine-patch.wasm")
  
+// Convert a Typst color to the hex string expected by the plugin
+#let color-hex(c) = rgb(c).to-hex()
+
+// Convert the color entries of an effect dictionary such as a shadow
+#let effect-params(effect) = {
+  let params = (:)
+  for (key, value) in effect {
+    params.insert(key, if key == "color" { color-hex(value) } else { value })
+  }
+  params
+}
+
+// Pass ratios in pins and anchors as percentage strings such as "30%"
+#let grid-params(pin) = {
+  let params = (:)
+  for (key, value) in pin {
+    params.insert(key, if type(value) == ratio { str(value / 1%) + "%" } else { value })
+  }
+  params
+}
+
+// Each u32 is 4 bytes in little-endian format
+#let bytes-to-u32(bytes, offset) = {
+  let b0 = bytes.at(offset)
+  let b1 = bytes.at(offset + 1) 
+  let b2 = bytes.at(offset + 2)
+  let b3 = bytes.at(offset + 3)
+  b0 + b1 * 256 + b2 * 256 * 256 + b3 * 256 * 256 * 256
+}
+
+// Collect the render options into the dictionary passed to the plugin
+#let render-options(
+  tint: none,
+  gradient-map: none,
+  color-swap: none,
+  opacity: none,
+  background: none,
+  flip-horizontal: false,
+  flip-vertical: false,
+  rotate: 0deg,
+  shadow: none,
+  glow: none,
+  source: none,
+  slice: none,
+  padding: none,
+  repeat: none,
+  outset: none,
+  border-width: none,
+  draw-center: true,
+  draw-edges: none,
+  pin: none,
+  anchor: none,
+  // Atlas page name from `atlas-region`, for choosing the image, not an option
+  page: none,
+) = {
+  let options = (:)
+  if tint != none {
+    options.insert("tint", color-hex(tint))
+  }
+  if gradient-map != none {
+    options.insert("gradient-map", gradient-map.map(color-hex))
+  }
+  if color-swap != none {
+    options.insert("color-swap", color-swap.map(((from, to)) => (color-hex(from), color-hex(to))))
+  }
+  if opacity != none {
+    // Accept both ratios (50%) and floats (0.5)
+    options.insert("opacity", if type(opacity) == ratio { opacity / 100% } else { float(opacity) })
+  }
+  if background != none {
+    options.insert("background", color-hex(background))
+  }
+  if flip-horizontal {
+    options.insert("flip-horizontal", true)
+  }
+  if flip-vertical {
+    options.insert("flip-vertical", true)
+  }
+  if rotate != 0deg {
+    // Normalize to a clockwise angle in 0..360 degrees
+    options.insert("rotate", calc.rem-euclid(int(rotate / 1deg), 360))
+  }
+  if shadow != none {
+    options.insert("shadow", effect-params(shadow))
+  }
+  if glow != none {
+    options.insert("glow", effect-params(glow))
+  }
+  if source != none {
+    // Region of a sprite sheet in pixels: (x:, y:, width:, height:)
+    options.insert("source", source)
+  }
+  if slice != none {
+    // Explicit insets (left:, top:, right:, bottom:) for images without marker border
+    options.insert("slice", slice)
+  }
+  if padding != none {
+    options.insert("padding", padding)
+  }
+  if repeat != none {
+    // "stretch", "tile", "tile-center", "tile-fit" or "space", for both axes or as (horizontal:, vertical:)
+    options.insert("repeat", if type(repeat) == str { (horizontal: repeat, vertical: repeat) } else { repeat })
+  }
+  if outset != none {
+    options.insert("outset", outset)
+  }
+  if border-width != none {
+    // Draw the fixed edges at these widths: (left:, top:, right:, bottom:)
+    options.insert("border-width", border-width)
+  }
+  if not draw-center {
+    options.insert("draw-center", false)
+  }
+  if draw-edges != none {
+    // Sides to draw, e.g. (left: false, right: false) for top and bottom rules only
+    options.insert("draw-edges", draw-edges)
+  }
+  if pin != none {
+    // Column or row index with a pixel or ratio size or offset: ((column: 1, size: 30%),)
+    options.insert("pin", pin.map(grid-params))
+  }
+  if anchor != none {
+    // Put a point of a fixed column or row at an offset: ((column: 2, offset: 30%, point: 0.5),)
+    options.insert("anchor", anchor.map(grid-params))
+  }
+  options
+}
+
+// Decode an image once inside the plugin, for scale-9patch to draw it many
+// times without passing the bytes again
+#let load-9patch(image-data) = (
+  handle: nine-patch-plugin.load(image-data),
+  data: image-data,
+)
+
+// The bytes of an image, given as bytes or as the result of load-9patch
+#let image-bytes(image-data) = if type(image-data) == dictionary { image-data.data } else { image-data }
+
+// Content, minimum and outset sizes of a nine-patch, by handle for the result
+// of load-9patch and from the bytes again once the plugin has evicted it
+#let content-info(image-data, options) = {
+  let info = if type(image-data) == dictionary {
+    nine-patch-plugin.info_handle(image-data.handle, options)
+  } else {
+    bytes(())
+  }
+  if info.len() == 0 {
+    info = nine-patch-plugin.nine_patch_content_info_with_options(image-bytes(image-data), options)
+  }
+  info
+}
+
   // Function to scale a nine-patch image
-#let scale-9patch(image-data, target-width, target-height, scale: 1pt) = {
+#let scale-9patch(image-data, target-width, target-height, scale: 1pt, ..options) = {
+
+  // Fractional pixel sizes: the last column and row are partly covered,
+  // so the image ends exactly at the target size
+  let width-px = target-width/scale
+  let height-px = target-height/scale
+
+  let options = cbor.encode(render-options(..options.named()))
+  let width-bytes = float(width-px).to-bytes(size: 4)
+  let height-bytes = float(height-px).to-bytes(size: 4)
+
+  // Images from load-9patch are rendered by handle, and from their bytes
+  // again once the plugin has evicted them
+  let result = if type(image-data) == dictionary {
+    nine-patch-plugin.render_handle(image-data.handle, width-bytes, height-bytes, options)
+  } else {
+    bytes(())
+  }
+  if result.len() == 0 {
+    result = nine-patch-plugin.nine_patch_fractional(image-bytes(image-data), width-bytes, height-bytes, options)
+  }
+
+  // Shadows and glows grow the image beyond the target size
+  let info = content-info(image-data, options)
+  let (left, top, right, bottom) = range(4).map(i => bytes-to-u32(info, 24 + i * 4) * scale)
+
+  box(
+    width: target-width,
+    height: target-height,
+    place(
+      dx: -left,
+      dy: -top,
+      image(result,
+        width: calc.ceil(width-px) * scale + left + right,
+        height: calc.ceil(height-px) * scale + top + bottom)
+    )
+  )
+}
+
+// Scale a nine-patch to many sizes with a single plugin call, such as the
+// cells of a table. Sizes are `(width, height)` pairs, the result is an
+// array of boxes in the same order.
+#let scale-9patches(image-data, sizes, scale: 1pt, ..options) = {
+  let options = render-options(..options.named())
+  let requests = sizes.map(((width, height)) => (float(width / scale), float(height / scale), options))
+  let results = cbor(nine-patch-plugin.nine_patch_batch(image-bytes(image-data), cbor.encode(requests)))
+
+  let info = content-info(image-data, cbor.encode(options))
+  let (left, top, right, bottom) = range(4).map(i => bytes-to-u32(info, 24 + i * 4) * scale)
+
+  sizes.zip(results).map((((width, height), result)) => box(
+    width: width,
+    height: height,
+    place(
+      dx: -left,
+      dy: -top,
+      image(result,
+        width: calc.ceil(width / scale) * scale + left + right,
+        height: calc.ceil(height / scale) * scale + top + bottom)
+    )
+  ))
+}
+
+// Size, spacing and shift of the tiles along one axis of a stretched slice,
+// matching the repeat modes of the plugin
+#let tile-layout(length, tile, mode) = {
+  if mode == "tile" {
+    (tile, 0pt, 0pt)
+  } else if mode == "tile-center" {
+    // One tile sits in the middle, the partial ones are cut at both ends
+    let start = calc.rem-euclid((length - tile) / 2 / 1pt, tile / 1pt) * 1pt
+    (tile, 0pt, if start > 0pt { start - tile } else { 0pt })
+  } else if mode == "tile-fit" {
+    (length / calc.max(1, calc.round(length / tile)), 0pt, 0pt)
+  } else if mode == "space" {
+    let count = calc.floor(length / tile)
+    let gap = (length - tile * count) / (count + 1)
+    (tile, gap, gap)
+  } else {
+    (length, 0pt, 0pt)
+  }
+}
+
+// The parsed columns and rows of a nine-patch, each with its pixel `start`,
+// `size` and whether it `stretch`es. Use the indices to pin them.
+#let grid-9patch(img, ..options) = cbor(
+  nine-patch-plugin.nine_patch_grid(image-bytes(img), cbor.encode(render-options(..options.named())))
+)
+
+
+// Draw a nine-patch from its unscaled slices instead of one rendered bitmap.
+// The stretched slices become `tiling` fills, so every slice is embedded once
+// and scales with the PDF. Shadows, glows, pins, anchors and backgrounds need
+// `scale-9patch`.
+#let tiled-9patch(image-data, target-width, target-height, scale: 1pt, ..options) = {
+  for key in ("pin", "anchor", "background") {
+    assert(options.named().at(key, default: none) == none, message: "tiled-9patch does not support " + key + ", use scale-9patch")
+  }
+
+  let slices = cbor(nine-patch-plugin.nine_patch_slices(
+    image-bytes(image-data),
+    cbor.encode(render-options(..options.named()))
+  ))
+
+  let outset = slices.outset
+  let width = target-width + (outset.left + outset.right) * scale
+  let height = target-height + (outset.top + outset.bottom) * scale
+
+  // The stretch tracks share the space left by the fixed ones in proportion
+  // to their size, counting empty ones as 1 pixel as the plugin does
+  let weight(t) = calc.max(t.size, 1)
+  let track-lengths(tracks, length) = {
+    let fixed = tracks.filter(t => not t.stretch).map(t => t.size * scale).sum(default: 0pt)
+    let stretch = tracks.filter(t => t.stretch).map(weight).sum(default: 0)
+    tracks.map(t => if t.stretch { (length - fixed) * weight(t) / stretch } else { t.size * scale })
+  }
+  let offsets(lengths) = range(lengths.len()).map(i => lengths.slice(0, i).sum(default: 0pt))
+
+  let column-widths = track-lengths(slices.columns, width)
+  let row-heights = track-lengths(slices.rows, height)
+  let column-offsets = offsets(column-widths)
+  let row-offsets = offsets(row-heights)
+
+  box(width: target-width, height: target-height, {
+    for cell in slices.cells {
+      let column = slices.columns.at(cell.column)
+      let row = slices.rows.at(cell.row)
+      let w = column-widths.at(cell.column)
+      let h = row-heights.at(cell.row)
+      if w <= 0pt or h <= 0pt { continue }
+
+      let (tile-w, gap-x, shift-x) = if column.stretch {
+        tile-layout(w, column.size * scale, slices.repeat.horizontal)
+      } else { (w, 0pt, 0pt) }
+      let (tile-h, gap-y, shift-y) = if row.stretch {
+        tile-layout(h, row.size * scale, slices.repeat.vertical)
+      } else { (h, 0pt, 0pt) }
+
+      let body = if tile-w == w and tile-h == h {
+        image(cell.image, width: w, height: h, fit: "stretch")
+      } else {
+        box(width: w, height: h, clip: true, place(
+          dx: shift-x,
+          dy: shift-y,
+          box(
+            width: w - shift-x,
+            height: h - shift-y,
+            fill: tiling(
+              size: (tile-w, tile-h),
+              spacing: (gap-x, gap-y),
+              image(cell.image, width: tile-w, height: tile-h, fit: "stretch")
+            )
+          )
+        ))
+      }
+
+      place(
+        dx: column-offsets.at(cell.column) - outset.left * scale,
+        dy: row-offsets.at(cell.row) - outset.top * scale,
+        body
+      )
+    }
+  })
+}
+
+
+// Scale every frame of an animated APNG or GIF nine-patch. The result is
+// encoded in the input format, for HTML exports and slide tools; Typst
+// itself only shows the first frame. Fractional sizes are rounded up, with
+// the last column and row partly covered as in scale-9patch.
+#let animated-9patch(image-data, target-width, target-height, scale: 1pt, ..options) = {
+  nine-patch-plugin.nine_patch_animated_fractional(
+    image-bytes(image-data),
+    float(target-width/scale).to-bytes(size: 4),
+    float(target-height/scale).to-bytes(size: 4),
+    cbor.encode(render-options(..options.named()))
+  )
+}
+
+
+// Show one frame of an animated nine-patch: `frame` is an index or a
+// `duration` into the animation, which loops
+#let still-9patch(image-data, target-width, target-height, frame: 0, scale: 1pt, ..options) = {
+  let selector = if type(frame) == duration {
+    (time: int(calc.round(frame.seconds() * 1000)))
+  } else {
+    (index: frame)
+  }
 
-  let width-bytes = int(target-width/scale).to-bytes()
-  let height-bytes = int(target-height/scale).to-bytes()
+  let width-px = target-width/scale
+  let height-px = target-height/scale
+  let options = cbor.encode(render-options(..options.named()))
 
-  let result = nine-patch-plugin.nine_patch(
-    image-data,
-    width-bytes,
-    height-bytes
+  let result = nine-patch-plugin.nine_patch_frame_fractional(
+    image-bytes(image-data),
+    float(width-px).to-bytes(size: 4),
+    float(height-px).to-bytes(size: 4),
+    cbor.encode(selector),
+    options
   )
 
-  image(result,
+  // Shadows and glows grow the image beyond the target size
+  let info = content-info(image-data, options)
+  let (left, top, right, bottom) = range(4).map(i => bytes-to-u32(info, 24 + i * 4) * scale)
+
+  box(
     width: target-width,
-    height: target-height)
+    height: target-height,
+    place(
+      dx: -left,
+      dy: -top,
+      image(result,
+        width: calc.ceil(width-px) * scale + left + right,
+        height: calc.ceil(height-px) * scale + top + bottom)
+    )
+  )
 }
 
 
-#let context-9patch(img, scale: 1pt) = {
-  let patch-bytes = nine-patch-plugin.nine_patch_content_info(img)
+// Frame count, frame delays and total duration of an animation, in milliseconds
+#let animation-info(image-data) = cbor(
+  nine-patch-plugin.animation_info(image-bytes(image-data))
+)
+
 
-  // Each u32 is 4 bytes in little-endian format
-  let bytes-to-u32(bytes, offset) = {
-    let b0 = bytes.at(offset)
-    let b1 = bytes.at(offset + 1) 
-    let b2 = bytes.at(offset + 2)
-    let b3 = bytes.at(offset + 3)
-    b0 + b1 * 256 + b2 * 256 * 256 + b3 * 256 * 256 * 256
+// Generate a rounded-rectangle nine-patch image without a source file.
+// Sizes are in image pixels, use the `scale` of `scale-9patch` to size them.
+#let frame-9patch(
+  radius: 8,
+  border-width: 1,
+  fill: white,
+  stroke: black,
+  shadow: none,
+  padding: none,
+) = {
+  let params = (
+    radius: radius,
+    border-width: border-width,
+    fill: color-hex(fill),
+    stroke: color-hex(stroke),
+  )
+  if shadow != none {
+    params.insert("shadow", effect-params(shadow))
+  }
+  if padding != none {
+    params.insert("padding", padding)
   }
+  nine-patch-plugin.generate_nine_patch(cbor.encode(params))
+}
+
+
+// Look up a region of a libGDX / Spine `.atlas` file. The result holds the
+// `source`, `slice` and `padding` options for rendering it from the page image,
+// and the `page` file name to find that image by.
+#let atlas-region(atlas, name) = cbor(
+  nine-patch-plugin.atlas_region(bytes(atlas), bytes(name))
+)
+
+
+// Look up the 9-slice border of a Unity sprite from its `.meta` file. Leave
+// `name` empty for single sprites. One Unity unit is drawn as `unit` long,
+// so the sprite's pixels per unit become the `scale` of the result.
+#let unity-sprite(img, meta, name: "", unit: 1cm) = {
+  let options = cbor(
+    nine-patch-plugin.unity_sprite(image-bytes(img), bytes(meta), bytes(name))
+  )
+  let pixels-per-unit = options.remove("pixels-per-unit")
+  options.insert("scale", unit / pixels-per-unit)
+  options
+}
+
+
+// Read the margins, region, tile modes and expand margins of a Godot
+// `StyleBoxTexture` resource or `NinePatchRect` node (`.tres` / `.tscn`)
+#let godot-style-box(resource) = cbor(
+  nine-patch-plugin.godot_style_box(bytes(resource))
+)
+
+
+// Read the slice, widths, outset and repeat modes of CSS `border-image`
+// declarations. The image referenced by `url()` is passed as `img`.
+#let css-border-image(img, css) = cbor(
+  nine-patch-plugin.css_border_image(image-bytes(img), bytes(css))
+)
+
+
+#let context-9patch(img, scale: 1pt, ..options) = {
+  let patch-bytes = content-info(img, cbor.encode(render-options(..options.named())))
   
   return (
     content-left: bytes-to-u32(patch-bytes, 0)*scale,
@@ -37,14 +449,45 @@
     content-right: bytes-to-u32(patch-bytes, 8)*scale,
     content-bottom: bytes-to-u32(patch-bytes, 12)*scale,
     min-width: bytes-to-u32(patch-bytes, 16)*scale,
-    min-height: bytes-to-u32(patch-bytes, 20)*scale
+    min-height: bytes-to-u32(patch-bytes, 20)*scale,
+    // Space drawn outside the target size by shadows and glows
+    outset-left: bytes-to-u32(patch-bytes, 24)*scale,
+    outset-top: bytes-to-u32(patch-bytes, 28)*scale,
+    outset-right: bytes-to-u32(patch-bytes, 32)*scale,
+    outset-bottom: bytes-to-u32(patch-bytes, 36)*scale
   )
 }
 
 
-#let auto-9patch(img, scale: 1pt, content) = {
+// Fixed column and row between the stretch runs, such as a speech bubble's tail
+#let aim-anchors(img, aim, scale, options) = {
+  let cells = grid-9patch(img, ..options)
+  let outset = options.at("outset", default: (:))
+  let anchors = ()
+
+  for (axis, key, index-key, side) in (("x", "columns", "column", "left"), ("y", "rows", "row", "top")) {
+    let target = aim.at(axis, default: none)
+    let segments = cells.at(key)
+    let index = range(1, calc.max(1, segments.len() - 1)).find(i => not segments.at(i).stretch)
+    if target != none and index != none {
+      // Offsets are measured in image pixels from the outset edge
+      anchors.push(((index-key): index, offset: target.to-absolute() / scale + outset.at(side, default: 0)))
+    }
+  }
+  anchors
+}
+
+
+// `aim` points the inner fixed segment, e.g. a bubble tail, at a position
+// in the box: (x: 120pt) or (y: 2em)
+#let auto-9patch(img, scale: 1pt, aim: none, ..options, content) = {
+
+  let options = options.named()
+  if aim != none {
+    options.insert("anchor", aim-anchors(img, aim, scale, options))
+  }
 
-  let im-data = context-9patch(img, scale: scale)
+  let im-data = context-9patch(img, scale: scale, ..options)
 
   let content-box = box(
     inset: (
@@ -61,18 +504,18 @@
   let w
   let h
   if (measurements.width < im-data.min-width) {
-    w = im-data.min_width
+    w = im-data.min-width
   } else {
     w = measurements.width
   }
   if (measurements.height < im-data.min-height) {
-    h = im-data.min_height
+    h = im-data.min-height
   } else {
     h = measurements.height
   }
 
   [
-    #place(scale-9patch(img, w, h,scale: scale))
+    #place(scale-9patch(img, w, h,scale: scale, ..options))
     #content-box
   ]
 }
\ No newline at end of file
@@ -0,0 +1,204 @@
+use std::io::Cursor;
+
+use image::codecs::gif::{GifDecoder, GifEncoder, Repeat};
+use image::codecs::png::PngDecoder;
+use image::{AnimationDecoder, Delay, Frame, Frames, ImageDecoder, ImageFormat, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use crate::limits;
+use crate::NinePatchError;
+
+// The container an animation was read from, and is written back to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnimationFormat {
+    Png,
+    Gif,
+}
+
+// Every frame of an APNG or GIF, composited to the full canvas, with its delay.
+// A still PNG is a single frame.
+pub struct Animation {
+    pub format: AnimationFormat,
+    pub frames: Vec<(RgbaImage, Delay)>,
+}
+
+pub fn decode_animation(bytes: &[u8]) -> Result<Animation, NinePatchError> {
+    let decode_error = |e| limits::decode_error(e, "Failed to decode animation");
+    let decoder_limits = limits::current().decoder_limits();
+
+    let format = image::guess_format(bytes).map_err(decode_error)?;
+    let (format, frames) = match format {
+        ImageFormat::Png => {
+            let decoder = PngDecoder::with_limits(Cursor::new(bytes), decoder_limits).map_err(decode_error)?;
+            let (width, height) = decoder.dimensions();
+            limits::check_input(width, height)?;
+
+            if decoder.is_apng().map_err(decode_error)? {
+                (AnimationFormat::Png, collect_frames(decoder.apng().map_err(decode_error)?.into_frames())?)
+            } else {
+                (AnimationFormat::Png, vec![Frame::new(crate::decode_image(bytes)?)])
+            }
+        }
+        ImageFormat::Gif => {
+            let mut decoder = GifDecoder::new(Cursor::new(bytes)).map_err(decode_error)?;
+            decoder.set_limits(decoder_limits).map_err(decode_error)?;
+            let (width, height) = decoder.dimensions();
+            limits::check_input(width, height)?;
+
+            (AnimationFormat::Gif, collect_frames(decoder.into_frames())?)
+        }
+        other => {
+            return Err(NinePatchError::InvalidImage(format!("Animations must be APNG or GIF, got {:?}", other)));
+        }
+    };
+
+    let frames = frames
+        .into_iter()
+        .map(|frame| {
+            let delay = frame.delay();
+            (frame.into_buffer(), delay)
+        })
+        .collect::<Vec<_>>();
+
+    if frames.is_empty() {
+        return Err(NinePatchError::InvalidImage("Animation has no frames".to_string()));
+    }
+
+    Ok(Animation { format, frames })
+}
+
+// Every frame is a full canvas, so the pixels of all frames together are
+// held to the input limit
+fn collect_frames(frames: Frames) -> Result<Vec<Frame>, NinePatchError> {
+    let max_pixels = limits::current().max_input_pixels;
+    let mut pixels = 0u64;
+    let mut collected = Vec::new();
+
+    for frame in frames {
+        let frame = frame.map_err(|e| limits::decode_error(e, "Failed to decode animation"))?;
+        let (width, height) = frame.buffer().dimensions();
+        pixels += width as u64 * height as u64;
+        if pixels > max_pixels {
+            return Err(NinePatchError::InputTooLarge(format!(
+                "the frames have more than the maximum of {} pixels together",
+                max_pixels
+            )));
+        }
+        collected.push(frame);
+    }
+
+    Ok(collected)
+}
+
+// Encode frames of equal size as an endlessly looping APNG or GIF
+pub fn encode_animation(format: AnimationFormat, frames: Vec<(RgbaImage, Delay)>) -> Result<Vec<u8>, NinePatchError> {
+    let encode_error = |e: &dyn std::fmt::Display| NinePatchError::InvalidFormat(format!("Failed to encode animation: {}", e));
+    let mut buffer = Vec::new();
+
+    match format {
+        AnimationFormat::Png => {
+            let (width, height) = frames.first().map_or((0, 0), |(image, _)| image.dimensions());
+            let mut encoder = png::Encoder::new(&mut buffer, width, height);
+            encoder.set_color(png::ColorType::Rgba);
+            encoder.set_depth(png::BitDepth::Eight);
+            encoder.set_animated(frames.len() as u32, 0).map_err(|e| encode_error(&e))?;
+
+            let mut writer = encoder.write_header().map_err(|e| encode_error(&e))?;
+            for (image, delay) in &frames {
+                // APNG delays are fractions of a second with 16-bit parts
+                writer.set_frame_delay(delay_ms(*delay).min(u16::MAX as u32) as u16, 1000).map_err(|e| encode_error(&e))?;
+                writer.write_image_data(image.as_raw()).map_err(|e| encode_error(&e))?;
+            }
+            writer.finish().map_err(|e| encode_error(&e))?;
+        }
+        AnimationFormat::Gif => {
+            let mut encoder = GifEncoder::new(&mut buffer);
+            encoder.set_repeat(Repeat::Infinite).map_err(|e| encode_error(&e))?;
+            encoder
+                .encode_frames(frames.into_iter().map(|(image, delay)| Frame::from_parts(image, 0, 0, delay)))
+                .map_err(|e| encode_error(&e))?;
+        }
+    }
+
+    Ok(buffer)
+}
+
+pub fn delay_ms(delay: Delay) -> u32 {
+    let (numerator, denominator) = delay.numer_denom_ms();
+    (numerator as f64 / denominator.max(1) as f64).round() as u32
+}
+
+// Which frame to show: `{"index": 2}` or `{"time": 350}` in milliseconds.
+// Times past the end wrap around, as the animation loops.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FrameSelector {
+    Index(usize),
+    Time(u32),
+}
+
+impl FrameSelector {
+    pub fn from_cbor(bytes: &[u8]) -> Result<Self, NinePatchError> {
+        if bytes.is_empty() {
+            return Ok(FrameSelector::Index(0));
+        }
+
+        ciborium::from_reader(bytes)
+            .map_err(|e| NinePatchError::InvalidFormat(format!("Failed to parse frame selector: {}", e)))
+    }
+
+    pub fn frame_index(self, delays: &[u32]) -> Result<usize, NinePatchError> {
+        match self {
+            FrameSelector::Index(index) if index < delays.len() => Ok(index),
+            FrameSelector::Index(index) => Err(NinePatchError::InvalidFormat(format!(
+                "Frame {} does not exist, the animation has {} frames",
+                index,
+                delays.len()
+            ))),
+            FrameSelector::Time(time) => {
+                let duration: u64 = delays.iter().map(|&delay| delay as u64).sum();
+                if duration == 0 {
+                    return Ok(0);
+                }
+
+                let mut remaining = time as u64 % duration;
+                for (index, &delay) in delays.iter().enumerate() {
+                    if remaining < delay as u64 {
+                        return Ok(index);
+                    }
+                    remaining -= delay as u64;
+                }
+                Ok(delays.len() - 1)
+            }
+        }
+    }
+}
+
+// Frame count and timing of an animation, in milliseconds
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct AnimationInfo {
+    pub frame_count: usize,
+    pub delays: Vec<u32>,
+    // Longer than a u32 holds after about 66 of the longest APNG frames
+    pub duration: u64,
+}
+
+impl AnimationInfo {
+    pub fn new(animation: &Animation) -> Self {
+        let delays: Vec<u32> = animation.frames.iter().map(|(_, delay)| delay_ms(*delay)).collect();
+
+        AnimationInfo {
+            frame_count: delays.len(),
+            duration: delays.iter().fold(0u64, |total, &delay| total.saturating_add(delay as u64)),
+            delays,
+        }
+    }
+
+    pub fn to_cbor(&self) -> Result<Vec<u8>, NinePatchError> {
+        let mut buffer = Vec::new();
+        ciborium::into_writer(self, &mut buffer)
+            .map_err(|e| NinePatchError::InvalidFormat(format!("Failed to encode animation info: {}", e)))?;
+        Ok(buffer)
+    }
+}
@@ -0,0 +1,156 @@
+use crate::options::{ImportedOptions, Insets, SourceRect};
+use crate::NinePatchError;
+
+// A region of a libGDX / Spine `.atlas` file
+#[derive(Debug, Default)]
+struct AtlasRegion {
+    name: String,
+    // Image file of the page holding the region
+    page: String,
+    // x, y, width, height with the size before packing rotation
+    bounds: Option<[u32; 4]>,
+    rotate: bool,
+    // left, right, top, bottom
+    split: Option<[u32; 4]>,
+    // left, right, top, bottom, -1 falls back to the split
+    pad: Option<[i32; 4]>,
+}
+
+// Resolve a named atlas region to its page and the source, slice and padding options
+pub fn region_options(atlas_text: &str, region_name: &str) -> Result<ImportedOptions, NinePatchError> {
+    let regions = parse_atlas(atlas_text)?;
+    let region = regions
+        .iter()
+        .find(|region| region.name == region_name)
+        .ok_or_else(|| NinePatchError::InvalidFormat(format!("Atlas has no region named {}", region_name)))?;
+
+    let [x, y, width, height] = region
+        .bounds
+        .ok_or_else(|| NinePatchError::InvalidFormat(format!("Atlas region {} has no bounds", region_name)))?;
+
+    // Rotated regions occupy the transposed rectangle in the page
+    let source = if region.rotate {
+        SourceRect { x, y, width: height, height: width, rotated: true }
+    } else {
+        SourceRect { x, y, width, height, rotated: false }
+    };
+
+    // Regions without split scale as a whole
+    let [left, right, top, bottom] = region.split.unwrap_or([0; 4]);
+    let slice = Insets { left, top, right, bottom };
+
+    let padding = match region.pad {
+        Some([pad_left, pad_right, pad_top, pad_bottom]) => {
+            let or_split = |pad: i32, split: u32| if pad < 0 { split } else { pad as u32 };
+            Insets {
+                left: or_split(pad_left, left),
+                top: or_split(pad_top, top),
+                right: or_split(pad_right, right),
+                bottom: or_split(pad_bottom, bottom),
+            }
+        }
+        // Without padding the content fills the center patch
+        None => slice,
+    };
+
+    Ok(ImportedOptions {
+        page: Some(region.page.clone()),
+        source: Some(source),
+        slice: Some(slice),
+        padding: Some(padding),
+        ..Default::default()
+    })
+}
+
+// Parse both the legacy format (indented `xy:`/`size:` fields) and the
+// compact format (`bounds:` fields) written since libGDX 1.10
+fn parse_atlas(text: &str) -> Result<Vec<AtlasRegion>, NinePatchError> {
+    let mut regions: Vec<AtlasRegion> = Vec::new();
+    let mut expect_page = true;
+    let mut page = String::new();
+    let mut in_region = false;
+    let mut xy = None;
+    let mut size = None;
+
+    for line in text.lines() {
+        let line = line.trim();
+
+        // Blank lines separate pages
+        if line.is_empty() {
+            expect_page = true;
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once(':') else {
+            if expect_page {
+                // Page image name, its fields follow until the first region
+                expect_page = false;
+                in_region = false;
+                page = line.to_string();
+            } else {
+                finish_legacy_bounds(regions.last_mut(), xy.take(), size.take());
+                regions.push(AtlasRegion {
+                    name: line.to_string(),
+                    page: page.clone(),
+                    ..Default::default()
+                });
+                in_region = true;
+            }
+            continue;
+        };
+
+        // Page fields such as size, format and filter are not needed
+        if !in_region {
+            continue;
+        }
+
+        let region = regions.last_mut().expect("region was started");
+        let key = key.trim();
+        let value = value.trim();
+
+        match key {
+            "xy" => xy = Some(parse_values::<u32, 2>(key, value)?),
+            "size" => size = Some(parse_values::<u32, 2>(key, value)?),
+            "bounds" => region.bounds = Some(parse_values(key, value)?),
+            "split" => region.split = Some(parse_values(key, value)?),
+            "pad" => region.pad = Some(parse_values(key, value)?),
+            "rotate" => {
+                region.rotate = match value {
+                    "true" | "90" => true,
+                    "false" | "0" => false,
+                    _ => {
+                        return Err(NinePatchError::InvalidFormat(format!(
+                            "Unsupported rotation {} of atlas region {}",
+                            value, region.name
+                        )))
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    finish_legacy_bounds(regions.last_mut(), xy, size);
+
+    Ok(regions)
+}
+
+// Combine the legacy `xy` and `size` fields into bounds
+fn finish_legacy_bounds(region: Option<&mut AtlasRegion>, xy: Option<[u32; 2]>, size: Option<[u32; 2]>) {
+    if let (Some(region), Some([x, y]), Some([width, height])) = (region, xy, size) {
+        if region.bounds.is_none() {
+            region.bounds = Some([x, y, width, height]);
+        }
+    }
+}
+
+fn parse_values<T: std::str::FromStr, const N: usize>(key: &str, value: &str) -> Result<[T; N], NinePatchError> {
+    let invalid = || NinePatchError::InvalidFormat(format!("Invalid atlas field {}: {}", key, value));
+
+    let values = value
+        .split(',')
+        .map(|part| part.trim().parse::<T>().map_err(|_| invalid()))
+        .collect::<Result<Vec<T>, NinePatchError>>()?;
+
+    values.try_into().map_err(|_| invalid())
+}
@@ -0,0 +1,77 @@
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+
+use ciborium::Value;
+use serde::Deserialize;
+use serde_bytes::ByteBuf;
+
+use crate::options::RenderOptions;
+use crate::{cache, decode_image, prepare_nine_patch, render_fractional, NinePatchError};
+
+// One image of a batch: `(width, height, options)`, with fractional sizes in
+// pixels. The options may be left out.
+#[derive(Deserialize)]
+struct BatchItem(Size, Size, #[serde(default)] RenderOptions);
+
+// Typst encodes whole numbers as integers
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Size {
+    Integer(u32),
+    Float(f32),
+}
+
+impl Size {
+    fn pixels(self) -> f32 {
+        match self {
+            Size::Integer(pixels) => pixels as f32,
+            Size::Float(pixels) => pixels,
+        }
+    }
+}
+
+// Render every requested size from a single decode of the image, returned as a
+// CBOR list of PNGs in request order. Identical requests are rendered once.
+pub fn render_batch(image_bytes: &[u8], requests: &[u8]) -> Result<Vec<u8>, NinePatchError> {
+    let requests: Vec<Value> = ciborium::from_reader(requests)
+        .map_err(|e| NinePatchError::InvalidFormat(format!("Failed to parse batch: {}", e)))?;
+
+    let image_hash = cache::hash_bytes(image_bytes);
+    let image = cache::cached_image(image_hash, || decode_image(image_bytes))?;
+
+    let mut patches = HashMap::new();
+    let mut rendered: HashMap<Vec<u8>, ByteBuf> = HashMap::new();
+    let mut outputs = Vec::with_capacity(requests.len());
+
+    for (index, request) in requests.iter().enumerate() {
+        // Requests are compared by their encoding
+        let mut key = Vec::new();
+        ciborium::into_writer(request, &mut key)
+            .map_err(|e| NinePatchError::InvalidFormat(format!("Failed to encode batch item {}: {}", index, e)))?;
+
+        if let Some(output) = rendered.get(&key) {
+            outputs.push(output.clone());
+            continue;
+        }
+
+        let BatchItem(width, height, options) = ciborium::from_reader(key.as_slice())
+            .map_err(|e| NinePatchError::InvalidFormat(format!("Failed to parse batch item {}: {}", index, e)))?;
+
+        // Items that differ only in styling share the parsed nine-patch
+        let patch = match patches.entry(cache::patch_key(image_hash, &options)) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                entry.insert(cache::cached_patch(image_hash, &options, || prepare_nine_patch(&image, &options))?)
+            }
+        };
+
+        let output = ByteBuf::from(render_fractional(patch, width.pixels(), height.pixels(), &options)?);
+        rendered.insert(key, output.clone());
+        outputs.push(output);
+    }
+
+    let mut buffer = Vec::new();
+    ciborium::into_writer(&outputs, &mut buffer)
+        .map_err(|e| NinePatchError::InvalidFormat(format!("Failed to encode batch: {}", e)))?;
+    Ok(buffer)
+}
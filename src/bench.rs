@@ -0,0 +1,32 @@
+use image::RgbaImage;
+
+use crate::grid::Grid;
+use crate::options::RenderOptions;
+use crate::NinePatchError;
+
+// The phases of `nine_patch_impl`, exposed with the `bench` feature so that
+// `benches/phases.rs` can time them one by one
+
+// Stretch markers read from the border, for `scale_nine_patch`
+pub struct Borders(Grid);
+
+// Decoding within the configured limits, converted to RGBA
+pub fn decode_image(image_bytes: &[u8]) -> Result<RgbaImage, NinePatchError> {
+    crate::decode_image(image_bytes)
+}
+
+pub fn parse_nine_patch_borders(img: &RgbaImage) -> Result<Borders, NinePatchError> {
+    crate::parse_nine_patch_borders(img).map(Borders)
+}
+
+pub fn extract_content(img: &RgbaImage) -> RgbaImage {
+    crate::extract_content(img)
+}
+
+pub fn scale_nine_patch(content: &RgbaImage, borders: &Borders, target_width: u32, target_height: u32) -> Result<RgbaImage, NinePatchError> {
+    crate::scale_nine_patch(content, &borders.0, target_width, target_height, &RenderOptions::default())
+}
+
+pub fn encode_png(img: &RgbaImage) -> Result<Vec<u8>, NinePatchError> {
+    crate::encode_png(img)
+}
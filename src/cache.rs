@@ -0,0 +1,232 @@
+use std::cell::RefCell;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::rc::Rc;
+
+use image::RgbaImage;
+use serde::Deserialize;
+
+use crate::options::RenderOptions;
+use crate::{NinePatch, NinePatchError};
+
+// Memory limits of the caches in bytes, 0 turns a cache off
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct CacheLimits {
+    pub asset_bytes: usize,
+    pub render_bytes: usize,
+}
+
+impl Default for CacheLimits {
+    fn default() -> Self {
+        CacheLimits {
+            asset_bytes: 64 * 1024 * 1024,
+            render_bytes: 32 * 1024 * 1024,
+        }
+    }
+}
+
+impl CacheLimits {
+    pub fn from_cbor(bytes: &[u8]) -> Result<Self, NinePatchError> {
+        if bytes.is_empty() {
+            return Ok(CacheLimits::default());
+        }
+
+        ciborium::from_reader(bytes)
+            .map_err(|e| NinePatchError::InvalidFormat(format!("Failed to parse cache limits: {}", e)))
+    }
+}
+
+// Least recently used entries are evicted first. Recency is a call counter
+// rather than a clock, so the same calls always keep the same entries.
+struct Lru<V> {
+    entries: HashMap<u64, (V, usize, u64)>,
+    limit: usize,
+    used: usize,
+    tick: u64,
+}
+
+impl<V: Clone> Lru<V> {
+    fn new(limit: usize) -> Self {
+        Lru {
+            entries: HashMap::new(),
+            limit,
+            used: 0,
+            tick: 0,
+        }
+    }
+
+    fn get(&mut self, key: u64) -> Option<V> {
+        self.tick += 1;
+        let tick = self.tick;
+
+        self.entries.get_mut(&key).map(|(value, _, last_used)| {
+            *last_used = tick;
+            value.clone()
+        })
+    }
+
+    fn insert(&mut self, key: u64, value: V, size: usize) {
+        // Entries larger than the whole cache are not kept
+        if size > self.limit {
+            return;
+        }
+
+        self.tick += 1;
+        if let Some((_, old_size, _)) = self.entries.insert(key, (value, size, self.tick)) {
+            self.used -= old_size;
+        }
+        self.used += size;
+        self.evict(self.limit);
+    }
+
+    // Finding the oldest entry scans them all, which is fine for the few large
+    // entries these caches hold but would need an ordered index for many
+    fn evict(&mut self, limit: usize) {
+        while self.used > limit {
+            let Some(oldest) = self.entries.iter().min_by_key(|(_, (_, _, last_used))| *last_used).map(|(key, _)| *key) else {
+                break;
+            };
+            if let Some((_, size, _)) = self.entries.remove(&oldest) {
+                self.used -= size;
+            }
+        }
+    }
+
+    fn set_limit(&mut self, limit: usize) {
+        self.limit = limit;
+        self.evict(limit);
+    }
+}
+
+// Decoded images and the nine-patches parsed from them share one limit. They
+// are shared rather than copied on every hit.
+#[derive(Clone)]
+enum Asset {
+    Image(Rc<RgbaImage>),
+    Patch(Rc<NinePatch>),
+}
+
+struct Caches {
+    assets: Lru<Asset>,
+    // Plugin functions return owned bytes, so renders are copied out either way
+    renders: Lru<Vec<u8>>,
+}
+
+thread_local! {
+    static CACHES: RefCell<Caches> = RefCell::new(Caches {
+        assets: Lru::new(CacheLimits::default().asset_bytes),
+        renders: Lru::new(CacheLimits::default().render_bytes),
+    });
+}
+
+pub fn set_limits(limits: CacheLimits) {
+    CACHES.with(|caches| {
+        let mut caches = caches.borrow_mut();
+        caches.assets.set_limit(limits.asset_bytes);
+        caches.renders.set_limit(limits.render_bytes);
+    });
+}
+
+pub fn clear() {
+    CACHES.with(|caches| {
+        let mut caches = caches.borrow_mut();
+        let (asset_bytes, render_bytes) = (caches.assets.limit, caches.renders.limit);
+        caches.assets.set_limit(0);
+        caches.renders.set_limit(0);
+        caches.assets.set_limit(asset_bytes);
+        caches.renders.set_limit(render_bytes);
+    });
+}
+
+// Hash of the image bytes, the key shared by everything cached for an image
+pub fn hash_bytes(bytes: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    bytes.hash(&mut hasher);
+    hasher.finish()
+}
+
+// The decoded image, stored under the hash of its bytes
+pub fn cached_image(
+    image_hash: u64,
+    load: impl FnOnce() -> Result<RgbaImage, NinePatchError>,
+) -> Result<Rc<RgbaImage>, NinePatchError> {
+    if let Some(image) = stored_image(image_hash) {
+        return Ok(image);
+    }
+
+    let image = Rc::new(load()?);
+    let size = image.as_raw().len();
+    CACHES.with(|caches| caches.borrow_mut().assets.insert(image_key(image_hash), Asset::Image(image.clone()), size));
+    Ok(image)
+}
+
+pub fn stored_image(image_hash: u64) -> Option<Rc<RgbaImage>> {
+    match CACHES.with(|caches| caches.borrow_mut().assets.get(image_key(image_hash))) {
+        Some(Asset::Image(image)) => Some(image),
+        _ => None,
+    }
+}
+
+fn image_key(image_hash: u64) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    "image".hash(&mut hasher);
+    image_hash.hash(&mut hasher);
+    hasher.finish()
+}
+
+// The key of the nine-patch for an image and the options that shape it
+pub fn patch_key(image_hash: u64, options: &RenderOptions) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    "patch".hash(&mut hasher);
+    image_hash.hash(&mut hasher);
+    options.source.hash(&mut hasher);
+    options.slice.hash(&mut hasher);
+    options.padding.hash(&mut hasher);
+    options.border_width.hash(&mut hasher);
+    options.flip_horizontal.hash(&mut hasher);
+    options.flip_vertical.hash(&mut hasher);
+    options.rotate.hash(&mut hasher);
+    hasher.finish()
+}
+
+// The decoded nine-patch for an image and the options that shape it
+pub fn cached_patch(
+    image_hash: u64,
+    options: &RenderOptions,
+    load: impl FnOnce() -> Result<NinePatch, NinePatchError>,
+) -> Result<Rc<NinePatch>, NinePatchError> {
+    let key = patch_key(image_hash, options);
+
+    if let Some(Asset::Patch(patch)) = CACHES.with(|caches| caches.borrow_mut().assets.get(key)) {
+        return Ok(patch);
+    }
+
+    let patch = Rc::new(load()?);
+    let size = patch.content.as_raw().len();
+    CACHES.with(|caches| caches.borrow_mut().assets.insert(key, Asset::Patch(patch.clone()), size));
+    Ok(patch)
+}
+
+// The encoded output of a plugin function for its exact arguments
+pub fn cached_render(
+    function: &str,
+    image_hash: u64,
+    arguments: &[&[u8]],
+    render: impl FnOnce() -> Result<Vec<u8>, NinePatchError>,
+) -> Result<Vec<u8>, NinePatchError> {
+    let mut hasher = DefaultHasher::new();
+    function.hash(&mut hasher);
+    image_hash.hash(&mut hasher);
+    arguments.hash(&mut hasher);
+    let key = hasher.finish();
+
+    if let Some(output) = CACHES.with(|caches| caches.borrow_mut().renders.get(key)) {
+        return Ok(output);
+    }
+
+    let output = render()?;
+    CACHES.with(|caches| caches.borrow_mut().renders.insert(key, output.clone(), output.len()));
+    Ok(output)
+}
@@ -0,0 +1,94 @@
+use image::Rgba;
+
+use crate::options::{Color, RenderOptions};
+
+// Style a pixel as it is written into the output: color swap, gradient map,
+// tint, opacity, and finally compositing over the background
+pub fn style_pixel(pixel: Rgba<u8>, options: &RenderOptions) -> Rgba<u8> {
+    let mut pixel = recolor_pixel(pixel, options);
+
+    if let Some(opacity) = options.opacity {
+        pixel[3] = (pixel[3] as f32 * opacity.clamp(0.0, 1.0)).round() as u8;
+    }
+
+    if let Some(background) = options.background {
+        pixel = composite_over(pixel, background.0);
+    }
+
+    pixel
+}
+
+fn recolor_pixel(pixel: Rgba<u8>, options: &RenderOptions) -> Rgba<u8> {
+    let mut pixel = pixel;
+
+    if let Some((_, to)) = options.color_swap.iter().find(|(from, _)| from.0 == pixel) {
+        pixel = to.0;
+    }
+
+    if !options.gradient_map.is_empty() {
+        let mapped = gradient_at(&options.gradient_map, luminance(pixel));
+        pixel = Rgba([mapped[0], mapped[1], mapped[2], multiply(pixel[3], mapped[3])]);
+    }
+
+    if let Some(tint) = options.tint {
+        pixel = Rgba([
+            multiply(pixel[0], tint.0[0]),
+            multiply(pixel[1], tint.0[1]),
+            multiply(pixel[2], tint.0[2]),
+            multiply(pixel[3], tint.0[3]),
+        ]);
+    }
+
+    pixel
+}
+
+// Rec. 601 luma in the range 0..=255
+fn luminance(pixel: Rgba<u8>) -> u8 {
+    let luma = 299 * pixel[0] as u32 + 587 * pixel[1] as u32 + 114 * pixel[2] as u32;
+    ((luma + 500) / 1000) as u8
+}
+
+// Linearly interpolate between evenly spaced gradient stops
+fn gradient_at(stops: &[Color], position: u8) -> Rgba<u8> {
+    if stops.len() == 1 {
+        return stops[0].0;
+    }
+
+    let segments = (stops.len() - 1) as u32;
+    let scaled = position as u32 * segments;
+    let index = (scaled / 255).min(segments - 1) as usize;
+    let t = scaled - index as u32 * 255;
+
+    let from = stops[index].0;
+    let to = stops[index + 1].0;
+    let mut result = [0u8; 4];
+    for (c, channel) in result.iter_mut().enumerate() {
+        let value = from[c] as u32 * (255 - t) + to[c] as u32 * t;
+        *channel = ((value + 127) / 255) as u8;
+    }
+
+    Rgba(result)
+}
+
+fn multiply(a: u8, b: u8) -> u8 {
+    ((a as u32 * b as u32 + 127) / 255) as u8
+}
+
+// Source-over compositing of non-premultiplied colors
+pub fn composite_over(src: Rgba<u8>, dst: Rgba<u8>) -> Rgba<u8> {
+    let src_alpha = src[3] as f32 / 255.0;
+    let dst_alpha = dst[3] as f32 / 255.0 * (1.0 - src_alpha);
+    let alpha = src_alpha + dst_alpha;
+    if alpha == 0.0 {
+        return Rgba([0, 0, 0, 0]);
+    }
+
+    let mut result = [0u8; 4];
+    for c in 0..3 {
+        let value = (src[c] as f32 * src_alpha + dst[c] as f32 * dst_alpha) / alpha;
+        result[c] = value.round() as u8;
+    }
+    result[3] = (alpha * 255.0).round() as u8;
+
+    Rgba(result)
+}
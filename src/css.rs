@@ -0,0 +1,331 @@
+use crate::options::{ImportedOptions, Insets, Repeat, TileMode};
+use crate::NinePatchError;
+
+// Resolve CSS `border-image` declarations to render options. The image is
+// supplied separately, so `url()` sources are skipped. Percentages of the
+// slice resolve against the image size.
+//
+// Two rules of CSS depend on the element and differ here: number widths and
+// outsets multiply the element's `border-width`, which is only known when it
+// is declared alongside and is otherwise taken to be the slice. And where
+// opposite edges do not fit the element CSS shrinks them proportionally,
+// while rendering fails with a target that is too small.
+pub fn border_image_options(text: &str, image_width: u32, image_height: u32) -> Result<ImportedOptions, NinePatchError> {
+    let mut border = BorderImage::default();
+    let mut declared_width = None;
+
+    for declaration in split_outside_parens(&strip_comments(text), ';') {
+        let Some((property, value)) = declaration.split_once(':') else {
+            continue;
+        };
+        let value = value.trim().trim_end_matches("!important").trim();
+
+        // Declarations may come wrapped in a rule, `.frame { ... }`
+        let property = property.rsplit('{').next().unwrap_or(property);
+
+        match property.trim().to_ascii_lowercase().as_str() {
+            "border-image" => border = parse_shorthand(value)?,
+            "border-image-source" => {}
+            "border-image-slice" => {
+                let (slice, fill) = parse_slice(&tokens(value))?;
+                border.slice = slice;
+                border.fill = fill;
+            }
+            "border-image-width" => border.width = Some(parse_values(&tokens(value))?),
+            "border-image-outset" => border.outset = Some(parse_values(&tokens(value))?),
+            "border-image-repeat" => border.repeat = parse_repeat(&tokens(value))?,
+            "border-width" => declared_width = Some(parse_values(&tokens(value))?),
+            _ => {}
+        }
+    }
+
+    let slice = Insets {
+        left: border.slice[3].resolve_slice(image_width)?,
+        top: border.slice[0].resolve_slice(image_height)?,
+        right: border.slice[1].resolve_slice(image_width)?,
+        bottom: border.slice[2].resolve_slice(image_height)?,
+    };
+
+    // The element's border, as wide as the slice unless declared
+    let element_width = match &declared_width {
+        Some(width) => Insets {
+            left: width[3].resolve_length()?,
+            top: width[0].resolve_length()?,
+            right: width[1].resolve_length()?,
+            bottom: width[2].resolve_length()?,
+        },
+        None => slice,
+    };
+
+    // Widths default to 1, numbers multiply the element's border and `auto` is the slice
+    let widths = match &border.width {
+        Some(width) => Insets {
+            left: width[3].resolve_multiple(element_width.left, slice.left)?,
+            top: width[0].resolve_multiple(element_width.top, slice.top)?,
+            right: width[1].resolve_multiple(element_width.right, slice.right)?,
+            bottom: width[2].resolve_multiple(element_width.bottom, slice.bottom)?,
+        },
+        None => element_width,
+    };
+
+    // Outset numbers multiply the element's border as well
+    let outset = match &border.outset {
+        Some(outset) => Insets {
+            left: outset[3].resolve_multiple(element_width.left, 0)?,
+            top: outset[0].resolve_multiple(element_width.top, 0)?,
+            right: outset[1].resolve_multiple(element_width.right, 0)?,
+            bottom: outset[2].resolve_multiple(element_width.bottom, 0)?,
+        },
+        None => Insets::default(),
+    };
+
+    Ok(ImportedOptions {
+        slice: Some(slice),
+        padding: Some(widths),
+        repeat: Some(border.repeat),
+        outset: (outset != Insets::default()).then_some(outset),
+        border_width: (widths != slice).then_some(widths),
+        // Without `fill` CSS leaves the middle of the element transparent
+        draw_center: (!border.fill).then_some(false),
+        ..Default::default()
+    })
+}
+
+// Initial values: 100% slice, width of 1 and outset of 0, stretched
+struct BorderImage {
+    slice: [Value; 4],
+    fill: bool,
+    width: Option<[Value; 4]>,
+    outset: Option<[Value; 4]>,
+    repeat: Repeat,
+}
+
+impl Default for BorderImage {
+    fn default() -> Self {
+        BorderImage {
+            slice: [Value::Percent(100.0); 4],
+            fill: false,
+            width: None,
+            outset: None,
+            repeat: Repeat::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Value {
+    Number(f32),
+    Percent(f32),
+    Pixels(f32),
+    Auto,
+}
+
+impl Value {
+    fn parse(token: &str) -> Result<Value, NinePatchError> {
+        let invalid = || NinePatchError::InvalidFormat(format!("Invalid border-image value: {}", token));
+        let number = |text: &str| text.parse::<f32>().ok().filter(|n| *n >= 0.0).ok_or_else(invalid);
+
+        if token.eq_ignore_ascii_case("auto") {
+            Ok(Value::Auto)
+        } else if let Some(percent) = token.strip_suffix('%') {
+            Ok(Value::Percent(number(percent)?))
+        } else if let Some(pixels) = token.strip_suffix("px") {
+            Ok(Value::Pixels(number(pixels)?))
+        } else {
+            Ok(Value::Number(number(token)?))
+        }
+    }
+
+    // Slices are image pixels or a percentage of the image size
+    fn resolve_slice(self, size: u32) -> Result<u32, NinePatchError> {
+        match self {
+            Value::Number(n) => Ok((n.round() as u32).min(size)),
+            Value::Percent(p) => Ok(((p / 100.0 * size as f32).round() as u32).min(size)),
+            _ => Err(NinePatchError::InvalidFormat("border-image-slice takes numbers and percentages".to_string())),
+        }
+    }
+
+    // Widths and outsets are pixel lengths or multiples of the border width,
+    // `auto` stands for the intrinsic width
+    fn resolve_multiple(self, border_width: u32, auto: u32) -> Result<u32, NinePatchError> {
+        match self {
+            Value::Number(n) => Ok((n * border_width as f32).round() as u32),
+            Value::Pixels(px) => Ok(px.round() as u32),
+            Value::Auto => Ok(auto),
+            Value::Percent(_) => Err(NinePatchError::InvalidFormat(
+                "Percentages of the element size are not supported for border-image widths".to_string(),
+            )),
+        }
+    }
+
+    // Border widths are pixel lengths, or a unitless 0
+    fn resolve_length(self) -> Result<u32, NinePatchError> {
+        match self {
+            Value::Pixels(px) => Ok(px.round() as u32),
+            Value::Number(0.0) => Ok(0),
+            _ => Err(NinePatchError::InvalidFormat("border-width takes pixel lengths".to_string())),
+        }
+    }
+}
+
+// `<source> || <slice> [/ <width> [/ <outset>]] || <repeat>`
+fn parse_shorthand(value: &str) -> Result<BorderImage, NinePatchError> {
+    let mut border = BorderImage::default();
+    let mut repeat = Vec::new();
+    let mut groups: Vec<Vec<String>> = vec![Vec::new()];
+
+    for token in tokens(value) {
+        let lower = token.to_ascii_lowercase();
+        if token.contains('(') || lower == "none" {
+            // The image itself is passed to the plugin separately
+            continue;
+        }
+        if matches!(lower.as_str(), "stretch" | "repeat" | "round" | "space") {
+            repeat.push(token);
+        } else if token == "/" {
+            groups.push(Vec::new());
+        } else if let Some(group) = groups.last_mut() {
+            group.push(token);
+        }
+    }
+
+    if groups.len() > 3 {
+        return Err(NinePatchError::InvalidFormat(format!("Too many / in border-image: {}", value)));
+    }
+
+    if !groups[0].is_empty() {
+        let (slice, fill) = parse_slice(&groups[0])?;
+        border.slice = slice;
+        border.fill = fill;
+    }
+    if let Some(width) = groups.get(1).filter(|group| !group.is_empty()) {
+        border.width = Some(parse_values(width)?);
+    }
+    if let Some(outset) = groups.get(2).filter(|group| !group.is_empty()) {
+        border.outset = Some(parse_values(outset)?);
+    }
+    if !repeat.is_empty() {
+        border.repeat = parse_repeat(&repeat)?;
+    }
+
+    Ok(border)
+}
+
+// Slice values with an optional `fill` keyword anywhere among them
+fn parse_slice(tokens: &[String]) -> Result<([Value; 4], bool), NinePatchError> {
+    let fill = tokens.iter().any(|token| token.eq_ignore_ascii_case("fill"));
+    let values: Vec<String> = tokens
+        .iter()
+        .filter(|token| !token.eq_ignore_ascii_case("fill"))
+        .cloned()
+        .collect();
+    Ok((parse_values(&values)?, fill))
+}
+
+// One to four values for top, right, bottom and left, as in CSS margins
+fn parse_values(tokens: &[String]) -> Result<[Value; 4], NinePatchError> {
+    let values = tokens.iter().map(|token| Value::parse(token)).collect::<Result<Vec<_>, _>>()?;
+
+    match values[..] {
+        [all] => Ok([all; 4]),
+        [vertical, horizontal] => Ok([vertical, horizontal, vertical, horizontal]),
+        [top, horizontal, bottom] => Ok([top, horizontal, bottom, horizontal]),
+        [top, right, bottom, left] => Ok([top, right, bottom, left]),
+        _ => Err(NinePatchError::InvalidFormat(format!("Expected 1 to 4 border-image values, got {}", values.len()))),
+    }
+}
+
+// One keyword for both axes, or horizontal then vertical
+fn parse_repeat(tokens: &[String]) -> Result<Repeat, NinePatchError> {
+    let modes = tokens.iter().map(|token| parse_repeat_keyword(token)).collect::<Result<Vec<_>, _>>()?;
+
+    match modes[..] {
+        [both] => Ok(Repeat { horizontal: both, vertical: both }),
+        [horizontal, vertical] => Ok(Repeat { horizontal, vertical }),
+        _ => Err(NinePatchError::InvalidFormat(format!("Expected 1 or 2 border-image-repeat values, got {}", modes.len()))),
+    }
+}
+
+// CSS `repeat` centers the tiles on each edge
+fn parse_repeat_keyword(token: &str) -> Result<TileMode, NinePatchError> {
+    match token.to_ascii_lowercase().as_str() {
+        "stretch" => Ok(TileMode::Stretch),
+        "repeat" => Ok(TileMode::TileCenter),
+        "round" => Ok(TileMode::TileFit),
+        "space" => Ok(TileMode::Space),
+        _ => Err(NinePatchError::InvalidFormat(format!("Invalid border-image-repeat value: {}", token))),
+    }
+}
+
+fn strip_comments(text: &str) -> String {
+    let mut result = String::new();
+    let mut rest = text;
+
+    while let Some(start) = rest.find("/*") {
+        result.push_str(&rest[..start]);
+        rest = rest[start + 2..].find("*/").map_or("", |end| &rest[start + 2 + end + 2..]);
+    }
+
+    result.push_str(rest);
+    result
+}
+
+// Split at a separator outside of parentheses, so that `url(a;b)` stays whole
+fn split_outside_parens(text: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+
+    for (i, c) in text.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            c if c == separator && depth == 0 => {
+                parts.push(&text[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+
+    parts.push(&text[start..]);
+    parts
+}
+
+// Whitespace separated tokens, with `/` as a token of its own and functions kept whole
+fn tokens(value: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut depth = 0;
+
+    for c in value.chars() {
+        match c {
+            '(' => {
+                depth += 1;
+                current.push(c);
+            }
+            ')' => {
+                depth -= 1;
+                current.push(c);
+            }
+            '/' if depth == 0 => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+                tokens.push("/".to_string());
+            }
+            c if c.is_whitespace() && depth == 0 => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+            }
+            _ => current.push(c),
+        }
+    }
+
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+
+    tokens
+}
@@ -0,0 +1,178 @@
+use image::{ImageBuffer, Rgba, RgbaImage};
+use serde::Deserialize;
+
+use crate::color::composite_over;
+use crate::options::{Color, RenderOptions};
+
+// Outer shadow drawn behind the rendered nine-patch
+#[derive(Debug, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Shadow {
+    pub offset_x: i32,
+    pub offset_y: i32,
+    pub blur: u32,
+    pub color: Color,
+}
+
+impl Default for Shadow {
+    fn default() -> Self {
+        Shadow {
+            offset_x: 0,
+            offset_y: 2,
+            blur: 4,
+            color: Color(Rgba([0, 0, 0, 128])),
+        }
+    }
+}
+
+// Glow around the rendered nine-patch, a shadow without offset
+#[derive(Debug, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Glow {
+    pub radius: u32,
+    pub color: Color,
+}
+
+impl Default for Glow {
+    fn default() -> Self {
+        Glow {
+            radius: 4,
+            color: Color(Rgba([255, 255, 255, 192])),
+        }
+    }
+}
+
+// Extra space the effects need around the target size, in pixels
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Margins {
+    pub left: u32,
+    pub top: u32,
+    pub right: u32,
+    pub bottom: u32,
+}
+
+pub fn effect_margins(options: &RenderOptions) -> Margins {
+    let mut margins = Margins::default();
+
+    if let Some(shadow) = &options.shadow {
+        let blur = shadow.blur as i64;
+        margins.left = margins.left.max((blur - shadow.offset_x as i64).clamp(0, u32::MAX as i64) as u32);
+        margins.right = margins.right.max((blur + shadow.offset_x as i64).clamp(0, u32::MAX as i64) as u32);
+        margins.top = margins.top.max((blur - shadow.offset_y as i64).clamp(0, u32::MAX as i64) as u32);
+        margins.bottom = margins.bottom.max((blur + shadow.offset_y as i64).clamp(0, u32::MAX as i64) as u32);
+    }
+
+    if let Some(glow) = &options.glow {
+        margins.left = margins.left.max(glow.radius);
+        margins.right = margins.right.max(glow.radius);
+        margins.top = margins.top.max(glow.radius);
+        margins.bottom = margins.bottom.max(glow.radius);
+    }
+
+    margins
+}
+
+// Grow the canvas by the effect margins and draw the glow and shadow behind the image
+pub fn apply_effects(img: RgbaImage, options: &RenderOptions) -> RgbaImage {
+    if options.shadow.is_none() && options.glow.is_none() {
+        return img;
+    }
+
+    let margins = effect_margins(options);
+    let (width, height) = img.dimensions();
+    let canvas_width = width + margins.left + margins.right;
+    let canvas_height = height + margins.top + margins.bottom;
+
+    // Alpha of the rendered image placed on the grown canvas
+    let mut alpha = vec![0.0f32; (canvas_width * canvas_height) as usize];
+    for (x, y, pixel) in img.enumerate_pixels() {
+        let index = ((y + margins.top) * canvas_width + x + margins.left) as usize;
+        alpha[index] = pixel[3] as f32 / 255.0;
+    }
+
+    let mut result = ImageBuffer::from_pixel(canvas_width, canvas_height, Rgba([0, 0, 0, 0]));
+
+    if let Some(shadow) = &options.shadow {
+        let blurred = blur_alpha(&alpha, canvas_width, canvas_height, shadow.blur);
+        draw_layer(&mut result, &blurred, shadow.color.0, shadow.offset_x, shadow.offset_y);
+    }
+
+    if let Some(glow) = &options.glow {
+        let blurred = blur_alpha(&alpha, canvas_width, canvas_height, glow.radius);
+        draw_layer(&mut result, &blurred, glow.color.0, 0, 0);
+    }
+
+    for (x, y, pixel) in img.enumerate_pixels() {
+        let target = result.get_pixel_mut(x + margins.left, y + margins.top);
+        *target = composite_over(*pixel, *target);
+    }
+
+    result
+}
+
+// Composite a colored alpha mask, shifted by the given offset, over the canvas
+fn draw_layer(canvas: &mut RgbaImage, mask: &[f32], color: Rgba<u8>, offset_x: i32, offset_y: i32) {
+    let (width, height) = canvas.dimensions();
+
+    for y in 0..height {
+        for x in 0..width {
+            let src_x = x as i64 - offset_x as i64;
+            let src_y = y as i64 - offset_y as i64;
+            if src_x < 0 || src_y < 0 || src_x >= width as i64 || src_y >= height as i64 {
+                continue;
+            }
+
+            let coverage = mask[(src_y as u32 * width + src_x as u32) as usize];
+            let layer = Rgba([color[0], color[1], color[2], (color[3] as f32 * coverage).round() as u8]);
+            let target = canvas.get_pixel_mut(x, y);
+            *target = composite_over(layer, *target);
+        }
+    }
+}
+
+// Separable Gaussian blur whose kernel reaches `radius` pixels
+fn blur_alpha(alpha: &[f32], width: u32, height: u32, radius: u32) -> Vec<f32> {
+    if radius == 0 {
+        return alpha.to_vec();
+    }
+
+    let sigma = radius as f32 / 2.0;
+    let kernel: Vec<f32> = (-(radius as i32)..=radius as i32)
+        .map(|i| (-(i * i) as f32 / (2.0 * sigma * sigma)).exp())
+        .collect();
+    let sum: f32 = kernel.iter().sum();
+    let kernel: Vec<f32> = kernel.iter().map(|k| k / sum).collect();
+
+    let (width, height) = (width as usize, height as usize);
+    let radius = radius as isize;
+    let mut horizontal = vec![0.0f32; alpha.len()];
+    let mut result = vec![0.0f32; alpha.len()];
+
+    for y in 0..height {
+        for x in 0..width {
+            let mut value = 0.0;
+            for (k, weight) in kernel.iter().enumerate() {
+                let sx = x as isize + k as isize - radius;
+                if sx >= 0 && (sx as usize) < width {
+                    value += alpha[y * width + sx as usize] * weight;
+                }
+            }
+            horizontal[y * width + x] = value;
+        }
+    }
+
+    for y in 0..height {
+        for x in 0..width {
+            let mut value = 0.0;
+            for (k, weight) in kernel.iter().enumerate() {
+                let sy = y as isize + k as isize - radius;
+                if sy >= 0 && (sy as usize) < height {
+                    value += horizontal[sy as usize * width + x] * weight;
+                }
+            }
+            result[y * width + x] = value;
+        }
+    }
+
+    result
+}
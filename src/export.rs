@@ -0,0 +1,87 @@
+use serde::Serialize;
+
+use crate::color::style_pixel;
+use crate::grid::Segment;
+use crate::options::{Insets, RenderOptions, Repeat};
+use crate::{encode_png, extract_region, NinePatch, NinePatchError};
+
+// The source slices of a nine-patch with the geometry to lay them out at any
+// size, so that the stretched sections can be drawn by Typst itself
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Slices {
+    pub columns: Vec<Segment>,
+    pub rows: Vec<Segment>,
+    pub cells: Vec<Cell>,
+    pub repeat: Repeat,
+    pub padding: Insets,
+    pub outset: Insets,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Cell {
+    pub column: usize,
+    pub row: usize,
+    #[serde(with = "serde_bytes")]
+    pub image: Vec<u8>,
+}
+
+impl Slices {
+    pub fn to_cbor(&self) -> Result<Vec<u8>, NinePatchError> {
+        let mut buffer = Vec::new();
+        ciborium::into_writer(self, &mut buffer)
+            .map_err(|e| NinePatchError::InvalidFormat(format!("Failed to encode slices: {}", e)))?;
+        Ok(buffer)
+    }
+}
+
+// Cut the loaded nine-patch into its cells, styled but not scaled. Empty and
+// hidden cells are left out.
+pub fn export_slices(patch: &NinePatch, options: &RenderOptions) -> Result<Slices, NinePatchError> {
+    // Effects are drawn around the flattened image and cannot be split into cells
+    if options.shadow.is_some() || options.glow.is_some() {
+        return Err(NinePatchError::InvalidFormat("Shadows and glows are not supported for exported slices".to_string()));
+    }
+    // Pins and anchors change the track sizes, which are laid out by the caller
+    if !options.pin.is_empty() || !options.anchor.is_empty() {
+        return Err(NinePatchError::InvalidFormat("Pins and anchors are not supported for exported slices".to_string()));
+    }
+    // A background would fill only the drawn cells, not hidden ones or the gaps between tiles
+    if options.background.is_some() {
+        return Err(NinePatchError::InvalidFormat("Backgrounds are not supported for exported slices".to_string()));
+    }
+
+    let columns = &patch.grid.columns;
+    let rows = &patch.grid.rows;
+    let mut cells = Vec::new();
+
+    for (row_index, row) in rows.iter().enumerate() {
+        for (column_index, column) in columns.iter().enumerate() {
+            let visible = patch.grid.is_visible(column_index, row_index, options);
+
+            if visible && column.size > 0 && row.size > 0 {
+                let mut region = extract_region(&patch.content, column.start, row.start, column.size, row.size);
+                for pixel in region.pixels_mut() {
+                    *pixel = style_pixel(*pixel, options);
+                }
+                cells.push(Cell { column: column_index, row: row_index, image: encode_png(&region)? });
+            }
+        }
+    }
+
+    let padding = &patch.padding;
+
+    Ok(Slices {
+        columns: columns.clone(),
+        rows: rows.clone(),
+        cells,
+        repeat: options.repeat,
+        padding: Insets {
+            left: padding.content_left,
+            top: padding.content_top,
+            right: padding.content_right,
+            bottom: padding.content_bottom,
+        },
+        outset: options.outset.unwrap_or_default(),
+    })
+}
@@ -0,0 +1,202 @@
+use image::{ImageBuffer, Rgba, RgbaImage};
+use serde::Deserialize;
+
+use crate::color::composite_over;
+use crate::effects::Shadow;
+use crate::limits;
+use crate::options::Color;
+use crate::NinePatchError;
+
+// Parameters for a procedurally generated rounded-rectangle frame, in pixels
+#[derive(Debug, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct FrameParams {
+    pub radius: u32,
+    pub border_width: u32,
+    pub fill: Color,
+    pub stroke: Color,
+    pub shadow: Option<Shadow>,
+    // Distance from the frame edge to the content area, defaults to the radius or border width
+    pub padding: Option<u32>,
+}
+
+impl Default for FrameParams {
+    fn default() -> Self {
+        FrameParams {
+            radius: 8,
+            border_width: 1,
+            fill: Color(Rgba([255, 255, 255, 255])),
+            stroke: Color(Rgba([0, 0, 0, 255])),
+            shadow: None,
+            padding: None,
+        }
+    }
+}
+
+impl FrameParams {
+    pub fn from_cbor(bytes: &[u8]) -> Result<Self, NinePatchError> {
+        if bytes.is_empty() {
+            return Ok(FrameParams::default());
+        }
+
+        let params: FrameParams = ciborium::from_reader(bytes)
+            .map_err(|e| NinePatchError::InvalidFormat(format!("Failed to parse frame parameters: {}", e)))?;
+        params.check_lengths()?;
+        Ok(params)
+    }
+
+    fn check_lengths(&self) -> Result<(), NinePatchError> {
+        let mut lengths = vec![("radius", self.radius as i64), ("border-width", self.border_width as i64)];
+        if let Some(shadow) = &self.shadow {
+            lengths.extend([
+                ("shadow blur", shadow.blur as i64),
+                ("shadow offset-x", (shadow.offset_x as i64).abs()),
+                ("shadow offset-y", (shadow.offset_y as i64).abs()),
+            ]);
+        }
+
+        match lengths.into_iter().find(|&(_, length)| length > MAX_LENGTH) {
+            Some((name, length)) => Err(NinePatchError::InvalidFormat(format!(
+                "Frame {} of {} is larger than the maximum of {}",
+                name, length, MAX_LENGTH
+            ))),
+            None => Ok(()),
+        }
+    }
+}
+
+// Largest radius, border width, blur and offset, far more than any output image
+// can hold while keeping the frame arithmetic small
+const MAX_LENGTH: i64 = u16::MAX as i64;
+
+// Synthesize an anti-aliased `.9.png` image, including its 1-pixel marker border
+pub fn generate_frame(params: &FrameParams) -> Result<RgbaImage, NinePatchError> {
+    let (offset_x, offset_y, blur) = match &params.shadow {
+        Some(shadow) => (shadow.offset_x, shadow.offset_y, shadow.blur),
+        None => (0, 0, 0),
+    };
+
+    // Margins around the frame that hold the shadow. The sizes are worked out
+    // in i64 and checked against the output limits before any use.
+    let (blur, offset_x, offset_y) = (blur as i64, offset_x as i64, offset_y as i64);
+    let margin_left = (blur - offset_x).max(0);
+    let margin_right = (blur + offset_x).max(0);
+    let margin_top = (blur - offset_y).max(0);
+    let margin_bottom = (blur + offset_y).max(0);
+
+    // Keep the single stretch pixel clear of the corners and their shadow,
+    // so that repeating it never smears a rounded or blurred edge
+    let corner = params.radius.max(params.border_width).max(1) as i64;
+    let corner_x = corner + blur + offset_x.abs();
+    let corner_y = corner + blur + offset_y.abs();
+    let frame_width = corner_x * 2 + 1;
+    let frame_height = corner_y * 2 + 1;
+
+    let content_width = margin_left + frame_width + margin_right;
+    let content_height = margin_top + frame_height + margin_bottom;
+
+    let clamp = |length: i64| length.clamp(0, u32::MAX as i64) as u32;
+    limits::check_output(clamp(content_width + 2), clamp(content_height + 2))?;
+
+    // Within the output limits every length fits a u32
+    let [margin_left, margin_top, corner, corner_x, corner_y, frame_width, frame_height, content_width, content_height] =
+        [margin_left, margin_top, corner, corner_x, corner_y, frame_width, frame_height, content_width, content_height].map(clamp);
+    let blur = blur as u32;
+
+    let frame = RoundedRect {
+        left: margin_left as f32,
+        top: margin_top as f32,
+        right: (margin_left + frame_width) as f32,
+        bottom: (margin_top + frame_height) as f32,
+        radius: params.radius as f32,
+    };
+    let inner = frame.inset(params.border_width as f32);
+
+    let mut img = ImageBuffer::from_pixel(content_width + 2, content_height + 2, Rgba([0, 0, 0, 0]));
+
+    for y in 0..content_height {
+        for x in 0..content_width {
+            let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+            let mut pixel = Rgba([0, 0, 0, 0]);
+
+            if let Some(shadow) = &params.shadow {
+                let d = frame.distance(px - offset_x as f32, py - offset_y as f32);
+                let coverage = if blur == 0 {
+                    (0.5 - d).clamp(0.0, 1.0)
+                } else {
+                    smoothstep((blur as f32 - d) / (2.0 * blur as f32))
+                };
+                pixel = with_coverage(shadow.color.0, coverage);
+            }
+
+            if params.border_width > 0 {
+                let outer_coverage = (0.5 - frame.distance(px, py)).clamp(0.0, 1.0);
+                pixel = composite_over(with_coverage(params.stroke.0, outer_coverage), pixel);
+            }
+
+            let inner_coverage = (0.5 - inner.distance(px, py)).clamp(0.0, 1.0);
+            pixel = composite_over(with_coverage(params.fill.0, inner_coverage), pixel);
+
+            img.put_pixel(x + 1, y + 1, pixel);
+        }
+    }
+
+    // Stretch markers on the top and left border
+    let black = Rgba([0, 0, 0, 255]);
+    img.put_pixel(1 + margin_left + corner_x, 0, black);
+    img.put_pixel(0, 1 + margin_top + corner_y, black);
+
+    // Content padding markers on the bottom and right border
+    let padding = params.padding.unwrap_or(corner).min(corner_x.min(corner_y));
+    for x in (margin_left + padding)..(margin_left + frame_width - padding) {
+        img.put_pixel(x + 1, content_height + 1, black);
+    }
+    for y in (margin_top + padding)..(margin_top + frame_height - padding) {
+        img.put_pixel(content_width + 1, y + 1, black);
+    }
+
+    Ok(img)
+}
+
+struct RoundedRect {
+    left: f32,
+    top: f32,
+    right: f32,
+    bottom: f32,
+    radius: f32,
+}
+
+impl RoundedRect {
+    fn inset(&self, amount: f32) -> RoundedRect {
+        RoundedRect {
+            left: self.left + amount,
+            top: self.top + amount,
+            right: self.right - amount,
+            bottom: self.bottom - amount,
+            radius: (self.radius - amount).max(0.0),
+        }
+    }
+
+    // Signed distance from a point to the outline, negative inside
+    fn distance(&self, x: f32, y: f32) -> f32 {
+        let half_width = ((self.right - self.left) / 2.0).max(0.0);
+        let half_height = ((self.bottom - self.top) / 2.0).max(0.0);
+        let radius = self.radius.min(half_width).min(half_height);
+
+        let qx = (x - (self.left + self.right) / 2.0).abs() - (half_width - radius);
+        let qy = (y - (self.top + self.bottom) / 2.0).abs() - (half_height - radius);
+
+        let outside = (qx.max(0.0).powi(2) + qy.max(0.0).powi(2)).sqrt();
+        let inside = qx.max(qy).min(0.0);
+        outside + inside - radius
+    }
+}
+
+fn with_coverage(color: Rgba<u8>, coverage: f32) -> Rgba<u8> {
+    Rgba([color[0], color[1], color[2], (color[3] as f32 * coverage).round() as u8])
+}
+
+fn smoothstep(t: f32) -> f32 {
+    let t = t.clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
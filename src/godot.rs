@@ -0,0 +1,152 @@
+use crate::options::{ImportedOptions, Insets, Repeat, SourceRect, TileMode};
+use crate::NinePatchError;
+
+// Resolve a Godot `StyleBoxTexture` resource or `NinePatchRect` node to render
+// options. Both Godot 3 and Godot 4 property names are understood.
+pub fn resource_options(text: &str) -> Result<ImportedOptions, NinePatchError> {
+    let sections = parse_sections(text);
+
+    // The resource itself, or the first embedded style box or nine-patch node
+    let properties = sections
+        .iter()
+        .find(|(header, _)| header == "resource")
+        .or_else(|| sections.iter().find(|(header, _)| header.contains("\"StyleBoxTexture\"")))
+        .or_else(|| sections.iter().find(|(header, _)| header.contains("\"NinePatchRect\"")))
+        .map(|(_, properties)| properties)
+        .ok_or_else(|| NinePatchError::InvalidFormat("No StyleBoxTexture or NinePatchRect found".to_string()))?;
+
+    let get = |names: &[&str]| {
+        names
+            .iter()
+            .find_map(|name| properties.iter().find(|(key, _)| key == name))
+            .map(|(_, value)| value.as_str())
+    };
+
+    let margin = |side: &str| -> Result<u32, NinePatchError> {
+        let names = [
+            format!("texture_margin_{}", side),
+            format!("patch_margin_{}", side),
+            format!("margin_{}", side),
+        ];
+        let names: Vec<&str> = names.iter().map(String::as_str).collect();
+        get(&names).map_or(Ok(0), parse_pixels)
+    };
+    let slice = Insets {
+        left: margin("left")?,
+        top: margin("top")?,
+        right: margin("right")?,
+        bottom: margin("bottom")?,
+    };
+
+    // Content margins of -1 fall back to the texture margins
+    let content_margin = |side: &str, fallback: u32| -> Result<u32, NinePatchError> {
+        match get(&[format!("content_margin_{}", side).as_str()]) {
+            Some(value) if parse_number(value)? >= 0.0 => parse_pixels(value),
+            _ => Ok(fallback),
+        }
+    };
+    let padding = Insets {
+        left: content_margin("left", slice.left)?,
+        top: content_margin("top", slice.top)?,
+        right: content_margin("right", slice.right)?,
+        bottom: content_margin("bottom", slice.bottom)?,
+    };
+
+    let expand_margin = |side: &str| -> Result<u32, NinePatchError> {
+        get(&[format!("expand_margin_{}", side).as_str()]).map_or(Ok(0), parse_pixels)
+    };
+    let outset = Insets {
+        left: expand_margin("left")?,
+        top: expand_margin("top")?,
+        right: expand_margin("right")?,
+        bottom: expand_margin("bottom")?,
+    };
+
+    let repeat = Repeat {
+        horizontal: get(&["axis_stretch_horizontal"]).map_or(Ok(TileMode::Stretch), parse_axis_stretch)?,
+        vertical: get(&["axis_stretch_vertical"]).map_or(Ok(TileMode::Stretch), parse_axis_stretch)?,
+    };
+
+    // An empty region rectangle stands for the whole texture
+    let source = match get(&["region_rect"]) {
+        Some(value) => {
+            let [x, y, width, height] = parse_rect2(value)?;
+            (width > 0 && height > 0).then_some(SourceRect { x, y, width, height, rotated: false })
+        }
+        None => None,
+    };
+
+    // Style boxes without a center leave it transparent
+    let draw_center = match get(&["draw_center"]) {
+        None | Some("true") => None,
+        Some("false") => Some(false),
+        Some(value) => return Err(NinePatchError::InvalidFormat(format!("Invalid draw_center: {}", value))),
+    };
+
+    Ok(ImportedOptions {
+        source,
+        slice: Some(slice),
+        padding: Some(padding),
+        repeat: Some(repeat),
+        outset: (outset != Insets::default()).then_some(outset),
+        draw_center,
+        ..Default::default()
+    })
+}
+
+// Split a `.tres` / `.tscn` file into its `[section]` headers and `key = value` properties
+fn parse_sections(text: &str) -> Vec<(String, Vec<(String, String)>)> {
+    let mut sections: Vec<(String, Vec<(String, String)>)> = Vec::new();
+
+    for line in text.lines() {
+        let line = line.trim();
+
+        if let Some(header) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            sections.push((header.trim().to_string(), Vec::new()));
+        } else if let Some((key, value)) = line.split_once('=') {
+            if let Some((_, properties)) = sections.last_mut() {
+                properties.push((key.trim().to_string(), value.trim().to_string()));
+            }
+        }
+    }
+
+    sections
+}
+
+fn parse_number(value: &str) -> Result<f32, NinePatchError> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| NinePatchError::InvalidFormat(format!("Invalid number: {}", value)))
+}
+
+fn parse_pixels(value: &str) -> Result<u32, NinePatchError> {
+    Ok(parse_number(value)?.max(0.0).round() as u32)
+}
+
+// `Rect2(x, y, width, height)`
+fn parse_rect2(value: &str) -> Result<[u32; 4], NinePatchError> {
+    let invalid = || NinePatchError::InvalidFormat(format!("Invalid Rect2: {}", value));
+
+    let inner = value
+        .strip_prefix("Rect2(")
+        .and_then(|v| v.strip_suffix(')'))
+        .ok_or_else(invalid)?;
+
+    let values = inner
+        .split(',')
+        .map(parse_pixels)
+        .collect::<Result<Vec<u32>, NinePatchError>>()?;
+
+    values.try_into().map_err(|_| invalid())
+}
+
+// AXIS_STRETCH_MODE_STRETCH, _TILE and _TILE_FIT
+fn parse_axis_stretch(value: &str) -> Result<TileMode, NinePatchError> {
+    match value {
+        "0" => Ok(TileMode::Stretch),
+        "1" => Ok(TileMode::Tile),
+        "2" => Ok(TileMode::TileFit),
+        _ => Err(NinePatchError::InvalidFormat(format!("Invalid axis stretch mode: {}", value))),
+    }
+}
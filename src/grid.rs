@@ -0,0 +1,305 @@
+use serde::{Deserialize, Serialize};
+
+use crate::options::RenderOptions;
+use crate::NinePatchError;
+
+// A run of columns or rows of the content, fixed or stretched
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Segment {
+    pub start: u32,
+    pub size: u32,
+    pub stretch: bool,
+}
+
+// The content split into columns and rows. Both axes start and end with a
+// fixed segment, which may be empty, and alternate between fixed and stretch
+// segments in between.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Grid {
+    pub columns: Vec<Segment>,
+    pub rows: Vec<Segment>,
+}
+
+impl Grid {
+    // Build the grid from the stretch runs of each axis, given as `(start, end)`
+    pub fn new(width: u32, height: u32, column_runs: &[(u32, u32)], row_runs: &[(u32, u32)]) -> Self {
+        Grid {
+            columns: segments(width, column_runs),
+            rows: segments(height, row_runs),
+        }
+    }
+
+    pub fn to_cbor(&self) -> Result<Vec<u8>, NinePatchError> {
+        let mut buffer = Vec::new();
+        ciborium::into_writer(self, &mut buffer)
+            .map_err(|e| NinePatchError::InvalidFormat(format!("Failed to encode grid: {}", e)))?;
+        Ok(buffer)
+    }
+
+    pub fn min_width(&self) -> u32 {
+        fixed_size(&self.columns)
+    }
+
+    pub fn min_height(&self) -> u32 {
+        fixed_size(&self.rows)
+    }
+
+    // Cells on the border are drawn with their sides, the others with the center
+    pub fn is_visible(&self, column: usize, row: usize, options: &RenderOptions) -> bool {
+        let edges = options.draw_edges;
+        let sides = [
+            (column == 0, edges.left),
+            (row == 0, edges.top),
+            (column == self.columns.len() - 1, edges.right),
+            (row == self.rows.len() - 1, edges.bottom),
+        ];
+
+        if sides.iter().any(|&(touches, _)| touches) {
+            sides.iter().any(|&(touches, drawn)| touches && drawn)
+        } else {
+            options.draw_center.unwrap_or(true)
+        }
+    }
+
+    // Mirror the grid left to right within a content of the given width
+    pub fn flip_horizontal(&self, width: u32) -> Grid {
+        Grid {
+            columns: mirror(&self.columns, width),
+            rows: self.rows.clone(),
+        }
+    }
+
+    pub fn flip_vertical(&self, height: u32) -> Grid {
+        Grid {
+            columns: self.columns.clone(),
+            rows: mirror(&self.rows, height),
+        }
+    }
+
+    // Rotate by 90 degrees clockwise: the rows, bottom first, become the columns
+    pub fn rotate_clockwise(&self, height: u32) -> Grid {
+        Grid {
+            columns: mirror(&self.rows, height),
+            rows: self.columns.clone(),
+        }
+    }
+}
+
+fn segments(length: u32, runs: &[(u32, u32)]) -> Vec<Segment> {
+    let mut segments = Vec::new();
+    let mut position = 0;
+
+    for &(start, end) in runs {
+        segments.push(Segment { start: position, size: start - position, stretch: false });
+        segments.push(Segment { start, size: end - start, stretch: true });
+        position = end;
+    }
+
+    segments.push(Segment { start: position, size: length - position, stretch: false });
+    segments
+}
+
+fn fixed_size(segments: &[Segment]) -> u32 {
+    segments.iter().filter(|s| !s.stretch).map(|s| s.size).sum()
+}
+
+fn mirror(segments: &[Segment], length: u32) -> Vec<Segment> {
+    segments
+        .iter()
+        .rev()
+        .map(|s| Segment { start: length - s.start - s.size, ..*s })
+        .collect()
+}
+
+// Give segments new sizes and list the source coordinate of every new
+// coordinate, sampling each segment evenly
+pub fn resample(segments: &[Segment], sizes: &[u32]) -> (Vec<Segment>, Vec<u32>) {
+    let mut resampled = Vec::new();
+    let mut lookup = Vec::new();
+
+    for (segment, &size) in segments.iter().zip(sizes) {
+        resampled.push(Segment { start: lookup.len() as u32, size, ..*segment });
+        for i in 0..size {
+            lookup.push(segment.start + (i * segment.size / size).min(segment.size.saturating_sub(1)));
+        }
+    }
+
+    (resampled, lookup)
+}
+
+// Fix the size of a stretch column or row, or the offset at which any
+// column or row starts. Exactly one of `column` and `row` is given.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Pin {
+    pub column: Option<usize>,
+    pub row: Option<usize>,
+    pub size: Option<Length>,
+    pub offset: Option<Length>,
+}
+
+// Place a point of a fixed column or row at an offset, such as the tip of a
+// speech bubble's tail. The point is a fraction of the segment, 0.5 its middle.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Anchor {
+    pub column: Option<usize>,
+    pub row: Option<usize>,
+    pub offset: Length,
+    #[serde(default = "middle")]
+    pub point: f32,
+}
+
+fn middle() -> f32 {
+    0.5
+}
+
+// Pixels, or a percentage of the drawn length such as "30%"
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(try_from = "LengthValue")]
+pub enum Length {
+    Pixels(f32),
+    Ratio(f32),
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum LengthValue {
+    Number(f32),
+    Text(String),
+}
+
+impl TryFrom<LengthValue> for Length {
+    type Error = NinePatchError;
+
+    fn try_from(value: LengthValue) -> Result<Self, Self::Error> {
+        match value {
+            LengthValue::Number(pixels) => Ok(Length::Pixels(pixels)),
+            LengthValue::Text(text) => text
+                .trim()
+                .strip_suffix('%')
+                .and_then(|percent| percent.trim().parse::<f32>().ok())
+                .map(|percent| Length::Ratio(percent / 100.0))
+                .ok_or_else(|| NinePatchError::InvalidFormat(format!("Invalid length: {}", text))),
+        }
+    }
+}
+
+impl Length {
+    fn resolve(self, length: u32) -> u32 {
+        match self {
+            Length::Pixels(pixels) => pixels.max(0.0).round() as u32,
+            Length::Ratio(ratio) => (ratio * length as f32).max(0.0).round() as u32,
+        }
+    }
+}
+
+pub enum Axis {
+    Horizontal,
+    Vertical,
+}
+
+// Drawn size of every segment for the given length. Stretch segments share the
+// space left by the fixed ones in proportion to their source size; pinned and
+// anchored offsets split the axis into parts that are shared out separately.
+pub fn layout(segments: &[Segment], length: u32, options: &RenderOptions, axis: Axis) -> Result<Vec<u32>, NinePatchError> {
+    let index = |column: Option<usize>, row: Option<usize>| match axis {
+        Axis::Horizontal => column,
+        Axis::Vertical => row,
+    };
+    let pins: Vec<(usize, &Pin)> = options
+        .pin
+        .iter()
+        .filter_map(|pin| index(pin.column, pin.row).map(|index| (index, pin)))
+        .collect();
+
+    let mut sizes: Vec<Option<u32>> = segments.iter().map(|s| (!s.stretch).then_some(s.size)).collect();
+    let mut offsets = Vec::new();
+
+    for &(index, pin) in &pins {
+        let segment = segments.get(index).ok_or_else(|| {
+            NinePatchError::InvalidFormat(format!("Pinned segment {} does not exist, there are {}", index, segments.len()))
+        })?;
+
+        if let Some(size) = pin.size {
+            if !segment.stretch {
+                return Err(NinePatchError::InvalidFormat(format!("Segment {} is fixed, its size cannot be pinned", index)));
+            }
+            sizes[index] = Some(size.resolve(length));
+        }
+        if let Some(offset) = pin.offset {
+            offsets.push((index, offset.resolve(length)));
+        }
+    }
+
+    // An anchor is an offset for the start of its segment
+    for anchor in &options.anchor {
+        let Some(index) = index(anchor.column, anchor.row) else {
+            continue;
+        };
+        let segment = segments.get(index).filter(|s| !s.stretch).ok_or_else(|| {
+            NinePatchError::InvalidFormat(format!("Anchored segment {} is not a fixed segment", index))
+        })?;
+
+        let point = (anchor.point.clamp(0.0, 1.0) * segment.size as f32).round() as u32;
+        offsets.push((index, anchor.offset.resolve(length).saturating_sub(point)));
+    }
+
+    offsets.sort_by_key(|&(index, _)| index);
+    offsets.dedup_by_key(|&mut (index, _)| index);
+
+    // Pinned sizes can add up beyond any length, which no target fits anyway
+    let min_size = |from: usize, to: usize| {
+        sizes[from..to].iter().flatten().map(|&size| size as u64).sum::<u64>().min(u32::MAX as u64) as u32
+    };
+    let flexible = |from: usize, to: usize| sizes[from..to].iter().any(Option::is_none);
+
+    // The start offsets of the parts, each kept within what the segments
+    // before and after it need
+    let mut boundaries = vec![(0, 0u32)];
+    for (index, offset) in offsets {
+        let &(previous_index, previous) = boundaries.last().unwrap();
+        let earliest = previous.saturating_add(min_size(previous_index, index));
+        let latest = length.saturating_sub(min_size(index, segments.len())).max(earliest);
+        let offset = if !flexible(previous_index, index) {
+            earliest
+        } else if !flexible(index, segments.len()) {
+            latest
+        } else {
+            offset.clamp(earliest, latest)
+        };
+        boundaries.push((index, offset));
+    }
+    boundaries.push((segments.len(), length));
+
+    let mut result = vec![0; segments.len()];
+    for pair in boundaries.windows(2) {
+        let ((from, start), (to, end)) = (pair[0], pair[1]);
+        let needed = min_size(from, to);
+        let extra = end.checked_sub(start.saturating_add(needed)).ok_or_else(|| {
+            NinePatchError::TargetTooSmall(format!("{} pixels do not fit the {} pixels of fixed and pinned segments", length, needed))
+        })?;
+
+        let weights: Vec<(usize, u32)> = (from..to)
+            .filter(|&i| sizes[i].is_none())
+            .map(|i| (i, segments[i].size.max(1)))
+            .collect();
+        let total: u64 = weights.iter().map(|&(_, weight)| weight as u64).sum();
+
+        // Round the running total so that the shares add up exactly
+        let mut shared = 0u64;
+        for &(i, weight) in &weights {
+            let before = (shared * extra as u64 / total.max(1)) as u32;
+            shared += weight as u64;
+            let after = (shared * extra as u64 / total.max(1)) as u32;
+            result[i] = after - before;
+        }
+        for i in from..to {
+            if let Some(size) = sizes[i] {
+                result[i] = size;
+            }
+        }
+    }
+
+    Ok(result)
+}
@@ -1,6 +1,28 @@
 use wasm_minimal_protocol::*;
-use image::{ImageBuffer, Rgba, RgbaImage, ImageFormat};
+use image::{ImageBuffer, ImageDecoder, Rgba, RgbaImage, ImageFormat};
 use std::io::Cursor;
+use std::rc::Rc;
+
+mod animation;
+mod atlas;
+mod batch;
+#[cfg(feature = "bench")]
+pub mod bench;
+mod cache;
+mod color;
+mod css;
+mod effects;
+mod export;
+mod generate;
+mod godot;
+mod grid;
+mod limits;
+mod options;
+mod transform;
+mod unity;
+
+use grid::{Axis, Grid};
+use options::{Insets, RenderOptions, SourceRect, TileMode};
 
 initiate_protocol!();
 
@@ -9,6 +31,9 @@ pub enum NinePatchError {
     InvalidImage(String),
     TargetTooSmall(String),
     InvalidFormat(String),
+    UnknownHandle(u64),
+    InputTooLarge(String),
+    OutputTooLarge(String),
 }
 
 impl std::fmt::Display for NinePatchError {
@@ -17,6 +42,9 @@ impl std::fmt::Display for NinePatchError {
             NinePatchError::InvalidImage(msg) => write!(f, "Invalid image: {}", msg),
             NinePatchError::TargetTooSmall(msg) => write!(f, "Target size too small: {}", msg),
             NinePatchError::InvalidFormat(msg) => write!(f, "Invalid format: {}", msg),
+            NinePatchError::UnknownHandle(handle) => write!(f, "Unknown handle {:016x}: the image was never loaded or has been evicted from the cache, load it again", handle),
+            NinePatchError::InputTooLarge(msg) => write!(f, "Input image too large: {}", msg),
+            NinePatchError::OutputTooLarge(msg) => write!(f, "Output image too large: {}", msg),
         }
     }
 }
@@ -29,11 +57,36 @@ pub fn nine_patch(
     width: &[u8],
     height: &[u8],
 ) -> Vec<u8> {
-    // Parse target dimensions
-    let target_width = u32::from_le_bytes([width[0], width[1], width[2], width[3]]);
-    let target_height = u32::from_le_bytes([height[0], height[1], height[2], height[3]]);
+    let result = cache::cached_render("nine_patch", cache::hash_bytes(image_bytes), &[width, height], || {
+        let target_width = parse_size(width)?;
+        let target_height = parse_size(height)?;
+        nine_patch_impl(image_bytes, target_width, target_height, &RenderOptions::default())
+    });
+    
+    match result {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("Nine-patch error: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+#[wasm_func]
+pub fn nine_patch_with_options(
+    image_bytes: &[u8],
+    width: &[u8],
+    height: &[u8],
+    options: &[u8],
+) -> Vec<u8> {
+    let result = cache::cached_render("nine_patch_with_options", cache::hash_bytes(image_bytes), &[width, height, options], || {
+        let target_width = parse_size(width)?;
+        let target_height = parse_size(height)?;
+        RenderOptions::from_cbor(options)
+            .and_then(|options| nine_patch_impl(image_bytes, target_width, target_height, &options))
+    });
     
-    match nine_patch_impl(image_bytes, target_width, target_height) {
+    match result {
         Ok(result) => result,
         Err(e) => {
             eprintln!("Nine-patch error: {}", e);
@@ -42,24 +95,145 @@ pub fn nine_patch(
     }
 }
 
-fn nine_patch_impl(image_bytes: &[u8], target_width: u32, target_height: u32) -> Result<Vec<u8>, NinePatchError> {
-    // Load the image
-    let img = image::load_from_memory(image_bytes)
-        .map_err(|e| NinePatchError::InvalidImage(format!("Failed to load image: {}", e)))?;
+// Whole target sizes are little-endian integers of 4 bytes, or of 8 bytes as
+// Typst's `int.to-bytes()` writes them by default
+fn parse_size(bytes: &[u8]) -> Result<u32, NinePatchError> {
+    match bytes.len() {
+        4 => Ok(u32::from_le_bytes(bytes.try_into().expect("length is 4"))),
+        8 => {
+            let size = u64::from_le_bytes(bytes.try_into().expect("length is 8"));
+            u32::try_from(size).map_err(|_| NinePatchError::InvalidFormat(format!("Size {} is too large", size)))
+        }
+        length => Err(NinePatchError::InvalidFormat(format!("Sizes are 4 or 8 bytes long, got {}", length))),
+    }
+}
+
+// Fractional target sizes are 4 little-endian bytes of an f32
+fn parse_float_bytes(bytes: &[u8]) -> Result<[u8; 4], NinePatchError> {
+    bytes.try_into().map_err(|_| {
+        NinePatchError::InvalidFormat(format!("Fractional sizes are 4 bytes long, got {}", bytes.len()))
+    })
+}
+
+fn nine_patch_impl(
+    image_bytes: &[u8],
+    target_width: u32,
+    target_height: u32,
+    options: &RenderOptions,
+) -> Result<Vec<u8>, NinePatchError> {
+    let result_img = render_nine_patch(image_bytes, target_width, target_height, options)?;
     
-    let rgba_img = img.to_rgba8();
-    let (orig_width, orig_height) = rgba_img.dimensions();
+    // Draw shadow and glow around the scaled image
+    let result_img = effects::apply_effects(result_img, options);
     
-    if orig_width < 3 || orig_height < 3 {
-        return Err(NinePatchError::InvalidImage("Image too small for nine-patch".to_string()));
+    encode_png(&result_img)
+}
+
+#[wasm_func]
+pub fn nine_patch_fractional(
+    image_bytes: &[u8],
+    width: &[u8],
+    height: &[u8],
+    options: &[u8],
+) -> Vec<u8> {
+    let result = cache::cached_render("nine_patch_fractional", cache::hash_bytes(image_bytes), &[width, height, options], || {
+        let target_width = f32::from_le_bytes(parse_float_bytes(width)?);
+        let target_height = f32::from_le_bytes(parse_float_bytes(height)?);
+        RenderOptions::from_cbor(options)
+            .and_then(|options| nine_patch_fractional_impl(image_bytes, target_width, target_height, &options))
+    });
+    
+    match result {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("Nine-patch error: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+// Render at the next whole pixel size and let the last column and row cover
+// only the fraction of a pixel that the target reaches into
+fn nine_patch_fractional_impl(
+    image_bytes: &[u8],
+    target_width: f32,
+    target_height: f32,
+    options: &RenderOptions,
+) -> Result<Vec<u8>, NinePatchError> {
+    let patch = load_nine_patch(image_bytes, options)?;
+    render_fractional(&patch, target_width, target_height, options)
+}
+
+fn render_fractional(
+    patch: &NinePatch,
+    target_width: f32,
+    target_height: f32,
+    options: &RenderOptions,
+) -> Result<Vec<u8>, NinePatchError> {
+    encode_png(&scale_fractional(patch, target_width, target_height, options)?)
+}
+
+// Scale to a fractional size and draw the effects around the result
+fn scale_fractional(
+    patch: &NinePatch,
+    target_width: f32,
+    target_height: f32,
+    options: &RenderOptions,
+) -> Result<RgbaImage, NinePatchError> {
+    if !(target_width.is_finite() && target_height.is_finite() && target_width >= 0.0 && target_height >= 0.0) {
+        return Err(NinePatchError::TargetTooSmall(format!("Invalid target size {}x{}", target_width, target_height)));
     }
     
-    // Parse nine-patch metadata from border pixels
-    let stretch_info = parse_nine_patch_borders(&rgba_img)?;
+    let mut result_img = scale_to_target(patch, target_width.ceil() as u32, target_height.ceil() as u32, options)?;
+    
+    let (width, height) = result_img.dimensions();
+    let coverage_x = target_width - target_width.floor();
+    let coverage_y = target_height - target_height.floor();
+    
+    if coverage_x > 0.0 && width > 0 {
+        for y in 0..height {
+            fade_pixel(result_img.get_pixel_mut(width - 1, y), coverage_x);
+        }
+    }
+    if coverage_y > 0.0 && height > 0 {
+        for x in 0..width {
+            fade_pixel(result_img.get_pixel_mut(x, height - 1), coverage_y);
+        }
+    }
+    
+    // Draw shadow and glow around the scaled image
+    Ok(effects::apply_effects(result_img, options))
+}
+
+fn fade_pixel(pixel: &mut Rgba<u8>, coverage: f32) {
+    pixel[3] = (pixel[3] as f32 * coverage).round() as u8;
+}
+
+// Scale the nine-patch to the target size plus outset, before any effects
+fn render_nine_patch(
+    image_bytes: &[u8],
+    target_width: u32,
+    target_height: u32,
+    options: &RenderOptions,
+) -> Result<RgbaImage, NinePatchError> {
+    let patch = load_nine_patch(image_bytes, options)?;
+    scale_to_target(&patch, target_width, target_height, options)
+}
+
+fn scale_to_target(
+    patch: &NinePatch,
+    target_width: u32,
+    target_height: u32,
+    options: &RenderOptions,
+) -> Result<RgbaImage, NinePatchError> {
+    // The outset draws the nine-patch beyond the target size
+    let outset = options.outset.unwrap_or_default();
+    let target_width = target_width.saturating_add(outset.left).saturating_add(outset.right);
+    let target_height = target_height.saturating_add(outset.top).saturating_add(outset.bottom);
     
     // Calculate minimum required size
-    let min_width = stretch_info.left_fixed + stretch_info.right_fixed;
-    let min_height = stretch_info.top_fixed + stretch_info.bottom_fixed;
+    let min_width = patch.grid.min_width();
+    let min_height = patch.grid.min_height();
     
     if target_width < min_width || target_height < min_height {
         return Err(NinePatchError::TargetTooSmall(
@@ -68,69 +242,735 @@ fn nine_patch_impl(image_bytes: &[u8], target_width: u32, target_height: u32) ->
         ));
     }
     
-    // Remove the outer border pixels to get the actual content
-    let content_img = extract_content(&rgba_img);
+    let (output_width, output_height) = effect_size(target_width, target_height, options);
+    limits::check_output(output_width, output_height)?;
     
     // Create the scaled nine-patch image
-    let result_img = scale_nine_patch(&content_img, &stretch_info, target_width, target_height)?;
+    scale_nine_patch(&patch.content, &patch.grid, target_width, target_height, options)
+}
+
+// Shadows and glows grow an image of the given size, outset included
+fn effect_size(width: u32, height: u32, options: &RenderOptions) -> (u32, u32) {
+    let margins = effects::effect_margins(options);
+    (
+        width.saturating_add(margins.left).saturating_add(margins.right),
+        height.saturating_add(margins.top).saturating_add(margins.bottom),
+    )
+}
+
+#[wasm_func]
+pub fn nine_patch_animated(
+    image_bytes: &[u8],
+    width: &[u8],
+    height: &[u8],
+    options: &[u8],
+) -> Vec<u8> {
+    let result = cache::cached_render("nine_patch_animated", cache::hash_bytes(image_bytes), &[width, height, options], || {
+        let target_width = parse_size(width)?;
+        let target_height = parse_size(height)?;
+        RenderOptions::from_cbor(options)
+            .and_then(|options| nine_patch_animated_impl(image_bytes, target_width as f32, target_height as f32, &options))
+    });
+    
+    match result {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("Animated nine-patch error: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+// `nine_patch_animated` with the target size as little-endian `f32` values, as
+// `nine_patch_fractional` takes it
+#[wasm_func]
+pub fn nine_patch_animated_fractional(
+    image_bytes: &[u8],
+    width: &[u8],
+    height: &[u8],
+    options: &[u8],
+) -> Vec<u8> {
+    let result = cache::cached_render("nine_patch_animated_fractional", cache::hash_bytes(image_bytes), &[width, height, options], || {
+        let target_width = f32::from_le_bytes(parse_float_bytes(width)?);
+        let target_height = f32::from_le_bytes(parse_float_bytes(height)?);
+        RenderOptions::from_cbor(options)
+            .and_then(|options| nine_patch_animated_impl(image_bytes, target_width, target_height, &options))
+    });
+    
+    match result {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("Animated nine-patch error: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+// Scale every frame of an APNG or GIF and encode them again in the same format
+fn nine_patch_animated_impl(
+    image_bytes: &[u8],
+    target_width: f32,
+    target_height: f32,
+    options: &RenderOptions,
+) -> Result<Vec<u8>, NinePatchError> {
+    let animation = animation::decode_animation(image_bytes)?;
+    
+    // Every scaled frame is held until the animation is encoded
+    let outset = options.outset.unwrap_or_default();
+    let (output_width, output_height) = effect_size(
+        (target_width.ceil() as u32).saturating_add(outset.left).saturating_add(outset.right),
+        (target_height.ceil() as u32).saturating_add(outset.top).saturating_add(outset.bottom),
+        options,
+    );
+    limits::check_output_frames(animation.frames.len(), output_width, output_height)?;
+    
+    let frames = load_frames(animation.frames, options)?
+        .into_iter()
+        .map(|(patch, delay)| Ok((scale_fractional(&patch, target_width, target_height, options)?, delay)))
+        .collect::<Result<Vec<_>, NinePatchError>>()?;
+    
+    animation::encode_animation(animation.format, frames)
+}
+
+#[wasm_func]
+pub fn nine_patch_frame(
+    image_bytes: &[u8],
+    width: &[u8],
+    height: &[u8],
+    frame: &[u8],
+    options: &[u8],
+) -> Vec<u8> {
+    let result = cache::cached_render("nine_patch_frame", cache::hash_bytes(image_bytes), &[width, height, frame, options], || {
+        let target_width = parse_size(width)?;
+        let target_height = parse_size(height)?;
+        let options = RenderOptions::from_cbor(options)?;
+        let frame = animation::FrameSelector::from_cbor(frame)?;
+        nine_patch_frame_impl(image_bytes, target_width as f32, target_height as f32, frame, &options)
+    });
+    
+    match result {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("Nine-patch frame error: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+// `nine_patch_frame` with the target size as little-endian `f32` values
+#[wasm_func]
+pub fn nine_patch_frame_fractional(
+    image_bytes: &[u8],
+    width: &[u8],
+    height: &[u8],
+    frame: &[u8],
+    options: &[u8],
+) -> Vec<u8> {
+    let result = cache::cached_render("nine_patch_frame_fractional", cache::hash_bytes(image_bytes), &[width, height, frame, options], || {
+        let target_width = f32::from_le_bytes(parse_float_bytes(width)?);
+        let target_height = f32::from_le_bytes(parse_float_bytes(height)?);
+        let options = RenderOptions::from_cbor(options)?;
+        let frame = animation::FrameSelector::from_cbor(frame)?;
+        nine_patch_frame_impl(image_bytes, target_width, target_height, frame, &options)
+    });
+    
+    match result {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("Nine-patch frame error: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+// Scale a single frame of an animation to a still PNG
+fn nine_patch_frame_impl(
+    image_bytes: &[u8],
+    target_width: f32,
+    target_height: f32,
+    frame: animation::FrameSelector,
+    options: &RenderOptions,
+) -> Result<Vec<u8>, NinePatchError> {
+    let animation = animation::decode_animation(image_bytes)?;
+    let index = frame.frame_index(&animation::AnimationInfo::new(&animation).delays)?;
+    
+    // Only the first frame is needed besides the requested one, for its markers
+    let first = prepare_nine_patch(&animation.frames[0].0, options)?;
+    let patch = match index {
+        0 => first,
+        index => load_frame(&animation.frames[index].0, index, Some(&first), options)?,
+    };
+    render_fractional(&patch, target_width, target_height, options)
+}
+
+#[wasm_func]
+pub fn animation_info(
+    image_bytes: &[u8],
+) -> Vec<u8> {
+    let result = animation::decode_animation(image_bytes)
+        .and_then(|animation| animation::AnimationInfo::new(&animation).to_cbor());
+    
+    match result {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("Animation info error: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+// Load every frame as a nine-patch
+fn load_frames(
+    frames: Vec<(RgbaImage, image::Delay)>,
+    options: &RenderOptions,
+) -> Result<Vec<(NinePatch, image::Delay)>, NinePatchError> {
+    let mut patches: Vec<(NinePatch, image::Delay)> = Vec::new();
+    
+    for (index, (frame, delay)) in frames.into_iter().enumerate() {
+        let patch = load_frame(&frame, index, patches.first().map(|(first, _)| first), options)?;
+        patches.push((patch, delay));
+    }
+    
+    Ok(patches)
+}
+
+// Prepare one frame of an animation. Frames without markers of their own take
+// those of the first frame, and frames with markers must agree with it.
+fn load_frame(
+    frame: &RgbaImage,
+    index: usize,
+    first: Option<&NinePatch>,
+    options: &RenderOptions,
+) -> Result<NinePatch, NinePatchError> {
+    let unmarked = options.slice.is_none() && !has_markers(frame, options.source);
+    let mut patch = prepare_nine_patch(frame, options)?;
+    
+    if let Some(first) = first {
+        if unmarked {
+            patch.grid = first.grid.clone();
+            patch.padding = first.padding.clone();
+        } else if patch.grid != first.grid || patch.padding != first.padding {
+            return Err(NinePatchError::InvalidFormat(
+                format!("Frame {} has different nine-patch markers than the first frame", index)
+            ));
+        }
+    }
+    
+    Ok(patch)
+}
+
+// Whether any pixel of the 1-pixel marker border, of the source region if
+// there is one, is visible
+fn has_markers(img: &RgbaImage, source: Option<SourceRect>) -> bool {
+    let source = source.unwrap_or(SourceRect { x: 0, y: 0, width: img.width(), height: img.height(), rotated: false });
+    let (right, bottom) = (source.x.saturating_add(source.width), source.y.saturating_add(source.height));
+    
+    (source.y..bottom.min(img.height())).any(|y| {
+        (source.x..right.min(img.width())).any(|x| {
+            let border = x == source.x || y == source.y || x + 1 == right || y + 1 == bottom;
+            border && img.get_pixel(x, y)[3] > 0
+        })
+    })
+}
+
+#[wasm_func]
+pub fn nine_patch_slices(
+    image_bytes: &[u8],
+    options: &[u8],
+) -> Vec<u8> {
+    let result = RenderOptions::from_cbor(options)
+        .and_then(|options| {
+            let patch = load_nine_patch(image_bytes, &options)?;
+            export::export_slices(&patch, &options)
+        })
+        .and_then(|slices| slices.to_cbor());
+    
+    match result {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("Nine-patch slices error: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+#[wasm_func]
+pub fn nine_patch_grid(
+    image_bytes: &[u8],
+    options: &[u8],
+) -> Vec<u8> {
+    let result = RenderOptions::from_cbor(options)
+        .and_then(|options| load_nine_patch(image_bytes, &options))
+        .and_then(|patch| patch.grid.to_cbor());
+    
+    match result {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("Nine-patch grid error: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+#[wasm_func]
+pub fn configure_cache(
+    limits: &[u8],
+) -> Vec<u8> {
+    match cache::CacheLimits::from_cbor(limits) {
+        Ok(limits) => cache::set_limits(limits),
+        Err(e) => eprintln!("Cache configuration error: {}", e),
+    }
+    
+    Vec::new()
+}
+
+// Render many sizes of one image, from a CBOR list of `(width, height, options)`
+#[wasm_func]
+pub fn nine_patch_batch(
+    image_bytes: &[u8],
+    requests: &[u8],
+) -> Vec<u8> {
+    let result = cache::cached_render("nine_patch_batch", cache::hash_bytes(image_bytes), &[requests], || {
+        batch::render_batch(image_bytes, requests)
+    });
+    
+    match result {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("Nine-patch batch error: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+// Set the largest input and output images, from CBOR. Cached images and renders
+// are dropped, as they were made under the previous limits.
+#[wasm_func]
+pub fn configure_limits(
+    params: &[u8],
+) -> Vec<u8> {
+    match limits::Limits::from_cbor(params) {
+        Ok(params) => {
+            limits::set_limits(params);
+            cache::clear();
+        }
+        Err(e) => eprintln!("Limits configuration error: {}", e),
+    }
+    
+    Vec::new()
+}
+
+// Decode an image once and return an 8-byte handle that the `_handle`
+// functions accept in place of the image bytes
+#[wasm_func]
+pub fn load(
+    image_bytes: &[u8],
+) -> Vec<u8> {
+    let handle = cache::hash_bytes(image_bytes);
+    
+    match cache::cached_image(handle, || decode_image(image_bytes)) {
+        Ok(_) => handle.to_le_bytes().to_vec(),
+        Err(e) => {
+            eprintln!("Nine-patch load error: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+// Like `nine_patch_fractional`, for a loaded image
+#[wasm_func]
+pub fn render_handle(
+    handle: &[u8],
+    width: &[u8],
+    height: &[u8],
+    options: &[u8],
+) -> Vec<u8> {
+    let result = parse_handle(handle).and_then(|handle| {
+        cache::cached_render("render_handle", handle, &[width, height, options], || {
+            let target_width = f32::from_le_bytes(parse_float_bytes(width)?);
+            let target_height = f32::from_le_bytes(parse_float_bytes(height)?);
+            let options = RenderOptions::from_cbor(options)?;
+            let patch = load_handle(handle, &options)?;
+            render_fractional(&patch, target_width, target_height, &options)
+        })
+    });
+    
+    match result {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("Nine-patch error: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+// Like `nine_patch_content_info_with_options`, for a loaded image
+#[wasm_func]
+pub fn info_handle(
+    handle: &[u8],
+    options: &[u8],
+) -> Vec<u8> {
+    let result = parse_handle(handle).and_then(|handle| {
+        let options = RenderOptions::from_cbor(options)?;
+        let patch = load_handle(handle, &options)?;
+        Ok(content_info_with_outsets(&patch, &options))
+    });
+    
+    match result {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("Nine-patch content info error: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+// Space drawn outside the target size: the outset plus the effect margins around it
+fn outsets(options: &RenderOptions) -> effects::Margins {
+    let outset = options.outset.unwrap_or_default();
+    let margins = effects::effect_margins(options);
     
-    // Encode as PNG
+    effects::Margins {
+        left: outset.left.saturating_add(margins.left),
+        top: outset.top.saturating_add(margins.top),
+        right: outset.right.saturating_add(margins.right),
+        bottom: outset.bottom.saturating_add(margins.bottom),
+    }
+}
+
+fn encode_png(img: &RgbaImage) -> Result<Vec<u8>, NinePatchError> {
     let mut buffer = Vec::new();
     {
         let mut cursor = Cursor::new(&mut buffer);
-        result_img.write_to(&mut cursor, ImageFormat::Png)
+        img.write_to(&mut cursor, ImageFormat::Png)
             .map_err(|e| NinePatchError::InvalidFormat(format!("Failed to encode PNG: {}", e)))?;
     }
     
     Ok(buffer)
 }
 
-#[derive(Debug)]
-struct StretchInfo {
-    left_fixed: u32,
-    right_fixed: u32,
-    top_fixed: u32,
-    bottom_fixed: u32,
-    stretch_left: u32,
-    stretch_right: u32,
-    stretch_top: u32,
-    stretch_bottom: u32,
-}
-
-fn parse_nine_patch_borders(img: &RgbaImage) -> Result<StretchInfo, NinePatchError> {
+#[wasm_func]
+pub fn atlas_region(
+    atlas_text: &[u8],
+    region_name: &[u8],
+) -> Vec<u8> {
+    let result = std::str::from_utf8(atlas_text)
+        .map_err(|e| NinePatchError::InvalidFormat(format!("Atlas is not UTF-8: {}", e)))
+        .and_then(|text| atlas::region_options(text, &String::from_utf8_lossy(region_name)))
+        .and_then(|options| options.to_cbor());
+    
+    match result {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("Atlas region error: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+#[wasm_func]
+pub fn unity_sprite(
+    image_bytes: &[u8],
+    meta_text: &[u8],
+    sprite_name: &[u8],
+) -> Vec<u8> {
+    let result = image_dimensions(image_bytes)
+        .and_then(|(width, height)| {
+            let text = std::str::from_utf8(meta_text)
+                .map_err(|e| NinePatchError::InvalidFormat(format!("Meta file is not UTF-8: {}", e)))?;
+            unity::sprite_options(text, &String::from_utf8_lossy(sprite_name), width, height)
+        })
+        .and_then(|options| options.to_cbor());
+    
+    match result {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("Unity sprite error: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+#[wasm_func]
+pub fn css_border_image(
+    image_bytes: &[u8],
+    css_text: &[u8],
+) -> Vec<u8> {
+    let result = image_dimensions(image_bytes)
+        .and_then(|(width, height)| {
+            let text = std::str::from_utf8(css_text)
+                .map_err(|e| NinePatchError::InvalidFormat(format!("CSS is not UTF-8: {}", e)))?;
+            css::border_image_options(text, width, height)
+        })
+        .and_then(|options| options.to_cbor());
+    
+    match result {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("CSS border-image error: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+// Read the image size from its header without decoding the pixels
+fn image_dimensions(image_bytes: &[u8]) -> Result<(u32, u32), NinePatchError> {
+    image::ImageReader::new(Cursor::new(image_bytes))
+        .with_guessed_format()
+        .map_err(|e| NinePatchError::InvalidImage(format!("Failed to read image: {}", e)))?
+        .into_dimensions()
+        .map_err(|e| NinePatchError::InvalidImage(format!("Failed to read image size: {}", e)))
+}
+
+#[wasm_func]
+pub fn godot_style_box(
+    resource_text: &[u8],
+) -> Vec<u8> {
+    let result = std::str::from_utf8(resource_text)
+        .map_err(|e| NinePatchError::InvalidFormat(format!("Resource is not UTF-8: {}", e)))
+        .and_then(godot::resource_options)
+        .and_then(|options| options.to_cbor());
+    
+    match result {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("Godot resource error: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+#[wasm_func]
+pub fn generate_nine_patch(
+    params: &[u8],
+) -> Vec<u8> {
+    let result = generate::FrameParams::from_cbor(params)
+        .and_then(|params| encode_png(&generate::generate_frame(&params)?));
+    
+    match result {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("Nine-patch generation error: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+// A decoded nine-patch: the image without its marker border plus the parsed metadata
+#[derive(Debug, Clone)]
+struct NinePatch {
+    content: RgbaImage,
+    grid: Grid,
+    padding: ContentInfo,
+}
+
+fn load_nine_patch(image_bytes: &[u8], options: &RenderOptions) -> Result<Rc<NinePatch>, NinePatchError> {
+    // Reuse the decoded and parsed image of an earlier call
+    let image_hash = cache::hash_bytes(image_bytes);
+    cache::cached_patch(image_hash, options, || {
+        let image = cache::cached_image(image_hash, || decode_image(image_bytes))?;
+        prepare_nine_patch(&image, options)
+    })
+}
+
+fn decode_image(image_bytes: &[u8]) -> Result<RgbaImage, NinePatchError> {
+    let load_error = |e| limits::decode_error(e, "Failed to load image");
+    
+    let mut decoder = image::ImageReader::new(Cursor::new(image_bytes))
+        .with_guessed_format()
+        .map_err(|e| NinePatchError::InvalidImage(format!("Failed to read image: {}", e)))?
+        .into_decoder()
+        .map_err(load_error)?;
+    decoder.set_limits(limits::current().decoder_limits()).map_err(load_error)?;
+    
+    // Check the size from the header before decoding the pixels
+    let (width, height) = decoder.dimensions();
+    limits::check_input(width, height)?;
+    
+    let img = image::DynamicImage::from_decoder(decoder).map_err(load_error)?;
+    
+    Ok(img.to_rgba8())
+}
+
+// The nine-patch of an image decoded by an earlier `load` call
+fn load_handle(handle: u64, options: &RenderOptions) -> Result<Rc<NinePatch>, NinePatchError> {
+    cache::cached_patch(handle, options, || {
+        let image = cache::stored_image(handle).ok_or(NinePatchError::UnknownHandle(handle))?;
+        prepare_nine_patch(&image, options)
+    })
+}
+
+fn parse_handle(handle: &[u8]) -> Result<u64, NinePatchError> {
+    let bytes: [u8; 8] = handle.try_into().map_err(|_| {
+        NinePatchError::InvalidFormat(format!("Handles are 8 bytes long, got {}", handle.len()))
+    })?;
+    
+    Ok(u64::from_le_bytes(bytes))
+}
+
+// Turn a decoded image, or one frame of an animation, into a nine-patch
+fn prepare_nine_patch(rgba_img: &RgbaImage, options: &RenderOptions) -> Result<NinePatch, NinePatchError> {
+    // Cut the nine-patch out of a sprite sheet
+    let cropped;
+    let rgba_img = match options.source {
+        Some(source) => {
+            cropped = crop_source(rgba_img, source)?;
+            &cropped
+        }
+        None => rgba_img,
+    };
+    
+    let mut patch = match options.slice {
+        Some(insets) => slice_with_insets(rgba_img, insets)?,
+        None => parse_marked_nine_patch(rgba_img)?,
+    };
+    
+    if let Some(padding) = options.padding {
+        patch.padding = ContentInfo {
+            content_left: padding.left,
+            content_top: padding.top,
+            content_right: padding.right,
+            content_bottom: padding.bottom,
+        };
+    }
+    
+    if let Some(widths) = options.border_width {
+        patch = resize_fixed(patch, widths)?;
+    }
+    
+    // Flip and rotate the content together with its metadata
+    transform::apply_transforms(patch, options)
+}
+
+// Read the stretch and padding markers from the 1-pixel border
+fn parse_marked_nine_patch(rgba_img: &RgbaImage) -> Result<NinePatch, NinePatchError> {
+    // Parse nine-patch metadata from border pixels
+    let grid = parse_nine_patch_borders(rgba_img)?;
+    let padding = parse_content_borders(rgba_img)?;
+    
+    // Remove the outer border pixels to get the actual content
+    let content = extract_content(rgba_img);
+    
+    Ok(NinePatch { content, grid, padding })
+}
+
+// Slice an image without marker border at fixed distances from its edges
+fn slice_with_insets(img: &RgbaImage, insets: Insets) -> Result<NinePatch, NinePatchError> {
+    let (width, height) = img.dimensions();
+    
+    if insets.left.saturating_add(insets.right) > width || insets.top.saturating_add(insets.bottom) > height {
+        return Err(NinePatchError::InvalidFormat(
+            format!("Slice insets {}, {}, {}, {} exceed the {}x{} image",
+                   insets.left, insets.top, insets.right, insets.bottom, width, height)
+        ));
+    }
+    
+    let grid = Grid::new(width, height, &[(insets.left, width - insets.right)], &[(insets.top, height - insets.bottom)]);
+    let padding = ContentInfo {
+        content_left: 0,
+        content_top: 0,
+        content_right: 0,
+        content_bottom: 0,
+    };
+    
+    Ok(NinePatch { content: img.clone(), grid, padding })
+}
+
+// Scale the outer fixed segments to new widths, keeping the others as they are
+fn resize_fixed(patch: NinePatch, widths: Insets) -> Result<NinePatch, NinePatchError> {
+    // A missing fixed segment has nothing to scale up
+    let sizes = |segments: &[grid::Segment], first: u32, last: u32| -> Vec<u32> {
+        let end = segments.len() - 1;
+        segments
+            .iter()
+            .enumerate()
+            .map(|(i, s)| match i {
+                _ if s.stretch || s.size == 0 || end == 0 => s.size,
+                0 => first,
+                i if i == end => last,
+                _ => s.size,
+            })
+            .collect()
+    };
+    
+    let grid = &patch.grid;
+    let column_sizes = sizes(&grid.columns, widths.left, widths.right);
+    let row_sizes = sizes(&grid.rows, widths.top, widths.bottom);
+    
+    // Check the new size before building the lookup tables
+    let total = |sizes: &[u32]| sizes.iter().map(|&size| size as u64).sum::<u64>().min(u32::MAX as u64) as u32;
+    let (width, height) = (total(&column_sizes), total(&row_sizes));
+    limits::check_output(width, height)?;
+    
+    let (columns, src_x) = grid::resample(&grid.columns, &column_sizes);
+    let (rows, src_y) = grid::resample(&grid.rows, &row_sizes);
+    
+    let content = ImageBuffer::from_fn(width, height, |x, y| {
+        *patch.content.get_pixel(src_x[x as usize], src_y[y as usize])
+    });
+    
+    Ok(NinePatch {
+        content,
+        grid: Grid { columns, rows },
+        padding: patch.padding,
+    })
+}
+
+fn crop_source(img: &RgbaImage, source: SourceRect) -> Result<RgbaImage, NinePatchError> {
     let (width, height) = img.dimensions();
+    let fits = source.x.checked_add(source.width).is_some_and(|right| right <= width)
+        && source.y.checked_add(source.height).is_some_and(|bottom| bottom <= height);
+    
+    if !fits {
+        return Err(NinePatchError::InvalidImage(
+            format!("Source region {}x{} at ({}, {}) exceeds the {}x{} image",
+                   source.width, source.height, source.x, source.y, width, height)
+        ));
+    }
+    
+    let region = image::imageops::crop_imm(img, source.x, source.y, source.width, source.height).to_image();
+    
+    // Undo the counter-clockwise rotation of packed regions
+    if source.rotated {
+        Ok(image::imageops::rotate90(&region))
+    } else {
+        Ok(region)
+    }
+}
+
+// The size of an image with a marker border, which needs at least one pixel
+// of content between its corners for the border arithmetic below
+fn marked_dimensions(img: &RgbaImage) -> Result<(u32, u32), NinePatchError> {
+    let (width, height) = img.dimensions();
+    
+    if width < 3 || height < 3 {
+        return Err(NinePatchError::InvalidImage("Image too small for nine-patch".to_string()));
+    }
+    
+    Ok((width, height))
+}
+
+fn parse_nine_patch_borders(img: &RgbaImage) -> Result<Grid, NinePatchError> {
+    let (width, height) = marked_dimensions(img)?;
     
     // Parse horizontal stretch regions from top border
-    let (stretch_left, stretch_right) = parse_stretch_line(img, 0, width, true)?;
+    let column_runs = parse_stretch_line(img, 0, width, true);
     
     // Parse vertical stretch regions from left border  
-    let (stretch_top, stretch_bottom) = parse_stretch_line(img, 0, height, false)?;
+    let row_runs = parse_stretch_line(img, 0, height, false);
     
     let content_width = width - 2; // Remove left and right borders
     let content_height = height - 2; // Remove top and bottom borders
     
-    let left_fixed = stretch_left;
-    let right_fixed = content_width - stretch_right;
-    let top_fixed = stretch_top;
-    let bottom_fixed = content_height - stretch_bottom;
-    
-    Ok(StretchInfo {
-        left_fixed,
-        right_fixed, 
-        top_fixed,
-        bottom_fixed,
-        stretch_left,
-        stretch_right,
-        stretch_top,
-        stretch_bottom,
-    })
+    Ok(Grid::new(content_width, content_height, &column_runs, &row_runs))
 }
 
-fn parse_stretch_line(img: &RgbaImage, coord: u32, length: u32, horizontal: bool) -> Result<(u32, u32), NinePatchError> {
+// Every run of black marker pixels, as (start, end) in content coordinates.
+// Without markers the whole content is fixed.
+fn parse_stretch_line(img: &RgbaImage, coord: u32, length: u32, horizontal: bool) -> Vec<(u32, u32)> {
     let black = Rgba([0, 0, 0, 255]);
     
-    let mut stretch_start = None;
-    let mut stretch_end = None;
+    let mut runs: Vec<(u32, u32)> = Vec::new();
     
     // Skip first and last pixels (corners)
     for i in 1..length-1 {
@@ -141,22 +981,15 @@ fn parse_stretch_line(img: &RgbaImage, coord: u32, length: u32, horizontal: bool
         };
         
         if pixel == black {
-            if stretch_start.is_none() {
-                stretch_start = Some(i - 1); // Convert to content coordinates
+            // Convert to content coordinates
+            match runs.last_mut() {
+                Some((_, end)) if *end == i - 1 => *end = i,
+                _ => runs.push((i - 1, i)),
             }
-            stretch_end = Some(i - 1); // Convert to content coordinates
         }
     }
     
-    match (stretch_start, stretch_end) {
-        (Some(start), Some(end)) => Ok((start, end + 1)),
-        _ => {
-            // If no stretch markers, treat entire content as non-stretchable
-            // Return (content_length, content_length) to indicate no stretch region
-            let content_length = length - 2;
-            Ok((content_length, content_length))
-        }
-    }
+    runs
 }
 
 fn extract_content(img: &RgbaImage) -> RgbaImage {
@@ -178,77 +1011,43 @@ fn extract_content(img: &RgbaImage) -> RgbaImage {
 
 fn scale_nine_patch(
     content: &RgbaImage,
-    stretch_info: &StretchInfo,
+    grid: &Grid,
     target_width: u32,
     target_height: u32,
+    options: &RenderOptions,
 ) -> Result<RgbaImage, NinePatchError> {
-    let (_content_width, _content_height) = content.dimensions();
-    
-    // Calculate stretch amounts
-    let extra_width = target_width - (stretch_info.left_fixed + stretch_info.right_fixed);
-    let extra_height = target_height - (stretch_info.top_fixed + stretch_info.bottom_fixed);
-    
-    let mut result = ImageBuffer::new(target_width, target_height);
-    
-    // Copy/scale the 9 patches
-    
-    // Top-left corner (fixed)
-    copy_region(content, &mut result, 
-               0, 0, stretch_info.left_fixed, stretch_info.top_fixed,
-               0, 0);
-    
-    // Top edge (stretch horizontally)
-    let top_stretch_width = stretch_info.stretch_right - stretch_info.stretch_left;
-    if top_stretch_width > 0 {
-        let top_section = extract_region(content, stretch_info.stretch_left, 0, top_stretch_width, stretch_info.top_fixed);
-        let scaled_top = resize_image(&top_section, extra_width, stretch_info.top_fixed);
-        copy_image(&scaled_top, &mut result, stretch_info.left_fixed, 0);
-    }
+    // Share the extra space out between the stretch segments
+    let widths = grid::layout(&grid.columns, target_width, options, Axis::Horizontal)?;
+    let heights = grid::layout(&grid.rows, target_height, options, Axis::Vertical)?;
     
-    // Top-right corner (fixed)
-    copy_region(content, &mut result,
-               stretch_info.stretch_right, 0, stretch_info.right_fixed, stretch_info.top_fixed,
-               stretch_info.left_fixed + extra_width, 0);
-    
-    // Left edge (stretch vertically)
-    let left_stretch_height = stretch_info.stretch_bottom - stretch_info.stretch_top;
-    if left_stretch_height > 0 {
-        let left_section = extract_region(content, 0, stretch_info.stretch_top, stretch_info.left_fixed, left_stretch_height);
-        let scaled_left = resize_image(&left_section, stretch_info.left_fixed, extra_height);
-        copy_image(&scaled_left, &mut result, 0, stretch_info.top_fixed);
-    }
-    
-    // Center (stretch both ways)
-    if top_stretch_width > 0 && left_stretch_height > 0 {
-        let center_section = extract_region(content, stretch_info.stretch_left, stretch_info.stretch_top, top_stretch_width, left_stretch_height);
-        let scaled_center = resize_image(&center_section, extra_width, extra_height);
-        copy_image(&scaled_center, &mut result, stretch_info.left_fixed, stretch_info.top_fixed);
-    }
-    
-    // Right edge (stretch vertically)
-    if left_stretch_height > 0 {
-        let right_section = extract_region(content, stretch_info.stretch_right, stretch_info.stretch_top, stretch_info.right_fixed, left_stretch_height);
-        let scaled_right = resize_image(&right_section, stretch_info.right_fixed, extra_height);
-        copy_image(&scaled_right, &mut result, stretch_info.left_fixed + extra_width, stretch_info.top_fixed);
-    }
+    // Start from the styled empty pixel so uncovered areas get the background too
+    let mut result = ImageBuffer::from_pixel(target_width, target_height, color::style_pixel(Rgba([0, 0, 0, 0]), options));
     
-    // Bottom-left corner (fixed)
-    copy_region(content, &mut result,
-               0, stretch_info.stretch_bottom, stretch_info.left_fixed, stretch_info.bottom_fixed,
-               0, stretch_info.top_fixed + extra_height);
-    
-    // Bottom edge (stretch horizontally)
-    if top_stretch_width > 0 {
-        let bottom_section = extract_region(content, stretch_info.stretch_left, stretch_info.stretch_bottom, top_stretch_width, stretch_info.bottom_fixed);
-        let scaled_bottom = resize_image(&bottom_section, extra_width, stretch_info.bottom_fixed);
-        copy_image(&scaled_bottom, &mut result, stretch_info.left_fixed, stretch_info.top_fixed + extra_height);
+    let mut dst_y = 0;
+    for (row_index, (row, &height)) in grid.rows.iter().zip(&heights).enumerate() {
+        let mut dst_x = 0;
+        for (column_index, (column, &width)) in grid.columns.iter().zip(&widths).enumerate() {
+            let visible = grid.is_visible(column_index, row_index, options);
+            
+            if visible && column.size > 0 && row.size > 0 && width > 0 && height > 0 {
+                if column.stretch || row.stretch {
+                    // Stretch or tile the cell along its stretch axes
+                    let section = extract_region(content, column.start, row.start, column.size, row.size);
+                    let horizontal = if column.stretch { options.repeat.horizontal } else { TileMode::Stretch };
+                    let vertical = if row.stretch { options.repeat.vertical } else { TileMode::Stretch };
+                    let scaled = fill_section(&section, width, height, horizontal, vertical);
+                    copy_image(&scaled, &mut result, dst_x, dst_y, options);
+                } else {
+                    // Fixed cells are copied as they are
+                    copy_region(content, &mut result, column.start, row.start, column.size, row.size, dst_x, dst_y, options);
+                }
+            }
+            
+            dst_x += width;
+        }
+        dst_y += height;
     }
     
-    // Bottom-right corner (fixed)
-    copy_region(content, &mut result,
-               stretch_info.stretch_right, stretch_info.stretch_bottom, stretch_info.right_fixed, stretch_info.bottom_fixed,
-               stretch_info.left_fixed + extra_width, stretch_info.top_fixed + extra_height);
-    
     Ok(result)
 }
 
@@ -267,21 +1066,85 @@ fn extract_region(img: &RgbaImage, x: u32, y: u32, width: u32, height: u32) -> R
     region
 }
 
-fn copy_region(src: &RgbaImage, dst: &mut RgbaImage, src_x: u32, src_y: u32, width: u32, height: u32, dst_x: u32, dst_y: u32) {
+#[allow(clippy::too_many_arguments)]
+fn copy_region(src: &RgbaImage, dst: &mut RgbaImage, src_x: u32, src_y: u32, width: u32, height: u32, dst_x: u32, dst_y: u32, options: &RenderOptions) {
     for dy in 0..height {
         for dx in 0..width {
             if src_x + dx < src.width() && src_y + dy < src.height() && 
                dst_x + dx < dst.width() && dst_y + dy < dst.height() {
                 let pixel = *src.get_pixel(src_x + dx, src_y + dy);
-                dst.put_pixel(dst_x + dx, dst_y + dy, pixel);
+                dst.put_pixel(dst_x + dx, dst_y + dy, color::style_pixel(pixel, options));
             }
         }
     }
 }
 
-fn copy_image(src: &RgbaImage, dst: &mut RgbaImage, dst_x: u32, dst_y: u32) {
+fn copy_image(src: &RgbaImage, dst: &mut RgbaImage, dst_x: u32, dst_y: u32, options: &RenderOptions) {
+    let (src_width, src_height) = src.dimensions();
+    copy_region(src, dst, 0, 0, src_width, src_height, dst_x, dst_y, options);
+}
+
+// Fill a section of the given size by stretching or tiling the source along each axis
+fn fill_section(src: &RgbaImage, new_width: u32, new_height: u32, horizontal: TileMode, vertical: TileMode) -> RgbaImage {
+    if horizontal == TileMode::Stretch && vertical == TileMode::Stretch {
+        return resize_image(src, new_width, new_height);
+    }
+    
     let (src_width, src_height) = src.dimensions();
-    copy_region(src, dst, 0, 0, src_width, src_height, dst_x, dst_y);
+    let mut dst = ImageBuffer::new(new_width, new_height);
+    
+    for y in 0..new_height {
+        for x in 0..new_width {
+            let src_x = map_coordinate(x, new_width, src_width, horizontal);
+            let src_y = map_coordinate(y, new_height, src_height, vertical);
+            
+            // Gaps between spaced tiles stay transparent
+            if let (Some(src_x), Some(src_y)) = (src_x, src_y) {
+                let pixel = *src.get_pixel(src_x, src_y);
+                dst.put_pixel(x, y, pixel);
+            }
+        }
+    }
+    
+    dst
+}
+
+// Map a destination coordinate to the source coordinate for a tile mode,
+// or to nothing for the gaps between spaced tiles
+fn map_coordinate(dst: u32, dst_length: u32, src_length: u32, mode: TileMode) -> Option<u32> {
+    let (dst, dst_length, src_length) = (dst as u64, dst_length as u64, src_length as u64);
+    
+    let src = match mode {
+        TileMode::Stretch => dst * src_length / dst_length,
+        TileMode::Tile => dst % src_length,
+        TileMode::TileCenter => {
+            // Center a tile in the space and repeat it in both directions
+            let start = (dst_length as i64 - src_length as i64) / 2;
+            (dst as i64 - start).rem_euclid(src_length as i64) as u64
+        }
+        TileMode::TileFit => {
+            // Round to the nearest whole number of tiles and squeeze them to fit
+            let tiles = ((dst_length + src_length / 2) / src_length).max(1);
+            (dst * tiles % dst_length) * src_length / dst_length
+        }
+        TileMode::Space => {
+            // Whole tiles with the leftover space spread evenly around them
+            let tiles = dst_length / src_length;
+            if tiles == 0 {
+                return None;
+            }
+            let gap = (dst_length - tiles * src_length) / (tiles + 1);
+            let start = (dst_length - tiles * src_length - gap * (tiles - 1)) / 2;
+            let position = dst.checked_sub(start)?;
+            let (tile, offset) = (position / (src_length + gap), position % (src_length + gap));
+            if tile >= tiles || offset >= src_length {
+                return None;
+            }
+            offset
+        }
+    };
+    
+    Some(src.min(src_length - 1) as u32)
 }
 
 // Simple nearest-neighbor image resize
@@ -311,7 +1174,7 @@ fn resize_image(src: &RgbaImage, new_width: u32, new_height: u32) -> RgbaImage {
 pub fn nine_patch_content_info(
     image_bytes: &[u8],
 ) -> Vec<u8> {
-    match nine_patch_content_info_impl(image_bytes) {
+    match nine_patch_content_info_impl(image_bytes, &RenderOptions::default()) {
         Ok(result) => result,
         Err(e) => {
             eprintln!("Nine-patch content info error: {}", e);
@@ -320,25 +1183,35 @@ pub fn nine_patch_content_info(
     }
 }
 
-fn nine_patch_content_info_impl(image_bytes: &[u8]) -> Result<Vec<u8>, NinePatchError> {
-    // Load the image
-    let img = image::load_from_memory(image_bytes)
-        .map_err(|e| NinePatchError::InvalidImage(format!("Failed to load image: {}", e)))?;
-    
-    let rgba_img = img.to_rgba8();
-    let (orig_width, orig_height) = rgba_img.dimensions();
+#[wasm_func]
+pub fn nine_patch_content_info_with_options(
+    image_bytes: &[u8],
+    options: &[u8],
+) -> Vec<u8> {
+    let result = RenderOptions::from_cbor(options)
+        .and_then(|options| nine_patch_content_info_with_options_impl(image_bytes, &options));
     
-    if orig_width < 3 || orig_height < 3 {
-        return Err(NinePatchError::InvalidImage("Image too small for nine-patch".to_string()));
+    match result {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("Nine-patch content info error: {}", e);
+            Vec::new()
+        }
     }
+}
+
+fn nine_patch_content_info_impl(image_bytes: &[u8], options: &RenderOptions) -> Result<Vec<u8>, NinePatchError> {
+    let patch = load_nine_patch(image_bytes, options)?;
+    Ok(content_info(&patch, options))
+}
+
+fn content_info(patch: &NinePatch, options: &RenderOptions) -> Vec<u8> {
+    let content_info = &patch.padding;
     
-    // Parse content padding from right and bottom borders
-    let content_info = parse_content_borders(&rgba_img)?;
-    
-    // Parse stretch info to calculate minimum dimensions
-    let stretch_info = parse_nine_patch_borders(&rgba_img)?;
-    let min_width = stretch_info.left_fixed + stretch_info.right_fixed;
-    let min_height = stretch_info.top_fixed + stretch_info.bottom_fixed;
+    // Calculate minimum dimensions from the fixed segments, the outset counts towards them
+    let outset = options.outset.unwrap_or_default();
+    let min_width = patch.grid.min_width().saturating_sub(outset.left.saturating_add(outset.right));
+    let min_height = patch.grid.min_height().saturating_sub(outset.top.saturating_add(outset.bottom));
     
     // Return as bytes: [content_left, content_top, content_right, content_bottom, min_width, min_height] as u32 little-endian
     let mut result = Vec::new();
@@ -349,10 +1222,28 @@ fn nine_patch_content_info_impl(image_bytes: &[u8]) -> Result<Vec<u8>, NinePatch
     result.extend_from_slice(&min_width.to_le_bytes());
     result.extend_from_slice(&min_height.to_le_bytes());
     
-    Ok(result)
+    result
 }
 
-#[derive(Debug)]
+fn nine_patch_content_info_with_options_impl(image_bytes: &[u8], options: &RenderOptions) -> Result<Vec<u8>, NinePatchError> {
+    let patch = load_nine_patch(image_bytes, options)?;
+    Ok(content_info_with_outsets(&patch, options))
+}
+
+fn content_info_with_outsets(patch: &NinePatch, options: &RenderOptions) -> Vec<u8> {
+    let mut result = content_info(patch, options);
+    
+    // Followed by the space drawn outside the target size: [outset_left, outset_top, outset_right, outset_bottom]
+    let margins = outsets(options);
+    result.extend_from_slice(&margins.left.to_le_bytes());
+    result.extend_from_slice(&margins.top.to_le_bytes());
+    result.extend_from_slice(&margins.right.to_le_bytes());
+    result.extend_from_slice(&margins.bottom.to_le_bytes());
+    
+    result
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 struct ContentInfo {
     content_left: u32,
     content_top: u32,
@@ -361,7 +1252,7 @@ struct ContentInfo {
 }
 
 fn parse_content_borders(img: &RgbaImage) -> Result<ContentInfo, NinePatchError> {
-    let (width, height) = img.dimensions();
+    let (width, height) = marked_dimensions(img)?;
     
     // Parse horizontal content region from bottom border (row height-1)
     let (content_left, content_right) = parse_content_line(img, height - 1, width, true)?;
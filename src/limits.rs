@@ -0,0 +1,115 @@
+use std::cell::Cell;
+
+use serde::Deserialize;
+
+use crate::NinePatchError;
+
+// Largest images the plugin decodes and allocates. A mistyped size in a
+// document would otherwise run the plugin out of memory and abort the compile.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Limits {
+    pub max_input_width: u32,
+    pub max_input_height: u32,
+    pub max_input_pixels: u64,
+    pub max_output_width: u32,
+    pub max_output_height: u32,
+    pub max_output_pixels: u64,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_input_width: 16384,
+            max_input_height: 16384,
+            max_input_pixels: 4096 * 4096,
+            max_output_width: 16384,
+            max_output_height: 16384,
+            max_output_pixels: 4096 * 4096,
+        }
+    }
+}
+
+impl Limits {
+    pub fn from_cbor(bytes: &[u8]) -> Result<Self, NinePatchError> {
+        if bytes.is_empty() {
+            return Ok(Limits::default());
+        }
+
+        ciborium::from_reader(bytes)
+            .map_err(|e| NinePatchError::InvalidFormat(format!("Failed to parse limits: {}", e)))
+    }
+
+    // Limits for the image decoders, which check them before allocating. The
+    // allocation limit leaves room for the decoders' own buffers.
+    pub fn decoder_limits(&self) -> image::Limits {
+        let mut limits = image::Limits::default();
+        limits.max_image_width = Some(self.max_input_width);
+        limits.max_image_height = Some(self.max_input_height);
+        limits.max_alloc = Some(self.max_input_pixels.saturating_mul(8));
+        limits
+    }
+}
+
+thread_local! {
+    static LIMITS: Cell<Limits> = Cell::new(Limits::default());
+}
+
+pub fn set_limits(limits: Limits) {
+    LIMITS.with(|current| current.set(limits));
+}
+
+pub fn current() -> Limits {
+    LIMITS.with(Cell::get)
+}
+
+pub fn check_input(width: u32, height: u32) -> Result<(), NinePatchError> {
+    let limits = current();
+    check(width, height, limits.max_input_width, limits.max_input_height, limits.max_input_pixels)
+        .map_err(NinePatchError::InputTooLarge)
+}
+
+// Checked before allocating an image of the given size
+pub fn check_output(width: u32, height: u32) -> Result<(), NinePatchError> {
+    let limits = current();
+    check(width, height, limits.max_output_width, limits.max_output_height, limits.max_output_pixels)
+        .map_err(NinePatchError::OutputTooLarge)
+}
+
+// The frames of an animation are all rendered before it is encoded, so their
+// pixels count together
+pub fn check_output_frames(frames: usize, width: u32, height: u32) -> Result<(), NinePatchError> {
+    check_output(width, height)?;
+
+    let max_pixels = current().max_output_pixels;
+    let pixels = (width as u64 * height as u64).saturating_mul(frames as u64);
+    if pixels > max_pixels {
+        return Err(NinePatchError::OutputTooLarge(format!(
+            "{} frames of {}x{} have {} pixels together, more than the maximum of {}",
+            frames, width, height, pixels, max_pixels
+        )));
+    }
+
+    Ok(())
+}
+
+fn check(width: u32, height: u32, max_width: u32, max_height: u32, max_pixels: u64) -> Result<(), String> {
+    if width > max_width || height > max_height {
+        return Err(format!("{}x{} exceeds the maximum of {}x{}", width, height, max_width, max_height));
+    }
+
+    let pixels = width as u64 * height as u64;
+    if pixels > max_pixels {
+        return Err(format!("{}x{} has {} pixels, more than the maximum of {}", width, height, pixels, max_pixels));
+    }
+
+    Ok(())
+}
+
+// Decoders report exceeded limits as their own kind of error
+pub fn decode_error(e: image::ImageError, context: &str) -> NinePatchError {
+    match e {
+        image::ImageError::Limits(e) => NinePatchError::InputTooLarge(e.to_string()),
+        e => NinePatchError::InvalidImage(format!("{}: {}", context, e)),
+    }
+}
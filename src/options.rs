@@ -0,0 +1,199 @@
+use image::Rgba;
+use serde::{Deserialize, Serialize};
+
+use crate::effects::{Glow, Shadow};
+use crate::grid::{Anchor, Pin};
+use crate::NinePatchError;
+
+// Render options, passed from Typst as a CBOR-encoded dictionary.
+// Every field is optional so that an empty dictionary renders like `nine_patch`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct RenderOptions {
+    // Multiplicative tint applied to every pixel
+    pub tint: Option<Color>,
+    // Colors evenly spaced from black to white, mapped by pixel luminance
+    pub gradient_map: Vec<Color>,
+    // Exact color replacements as (from, to) pairs
+    pub color_swap: Vec<(Color, Color)>,
+    // Global opacity between 0.0 and 1.0
+    pub opacity: Option<f32>,
+    // Solid color the nine-patch is composited over
+    pub background: Option<Color>,
+    // Mirror the nine-patch before scaling
+    pub flip_horizontal: bool,
+    pub flip_vertical: bool,
+    // Clockwise rotation in degrees, applied after flipping
+    pub rotate: u32,
+    // Effects drawn behind the scaled nine-patch, growing the canvas
+    pub shadow: Option<Shadow>,
+    pub glow: Option<Glow>,
+    // Region of the source image holding the nine-patch, for sprite sheets
+    pub source: Option<SourceRect>,
+    // Explicit slice insets for images without a marker border
+    pub slice: Option<Insets>,
+    // Content padding, overriding the padding markers
+    pub padding: Option<Insets>,
+    // How the edges and center fill the stretched space
+    pub repeat: Repeat,
+    // Draw the nine-patch this far beyond the target size
+    pub outset: Option<Insets>,
+    // Draw the fixed edges at these widths instead of their source size
+    pub border_width: Option<Insets>,
+    // Draw the center section, defaults to true
+    pub draw_center: Option<bool>,
+    // Draw the sections along each side, a corner is drawn with either of its sides
+    pub draw_edges: Edges,
+    // Sizes and offsets of chosen columns and rows
+    pub pin: Vec<Pin>,
+    // Fixed columns and rows placed at an offset, like a speech bubble's tail
+    pub anchor: Vec<Anchor>,
+}
+
+impl RenderOptions {
+    pub fn from_cbor(bytes: &[u8]) -> Result<Self, NinePatchError> {
+        if bytes.is_empty() {
+            return Ok(RenderOptions::default());
+        }
+
+        ciborium::from_reader(bytes)
+            .map_err(|e| NinePatchError::InvalidFormat(format!("Failed to parse options: {}", e)))
+    }
+}
+
+// Render options derived from the metadata of another tool, returned to Typst
+// as a CBOR dictionary that can be passed on as named arguments
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ImportedOptions {
+    // Atlas page image the options refer to, for the caller to pick the image
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<SourceRect>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub slice: Option<Insets>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub padding: Option<Insets>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repeat: Option<Repeat>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub outset: Option<Insets>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub border_width: Option<Insets>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub draw_center: Option<bool>,
+    // Source pixels per unit of length, turned into `scale` on the Typst side
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pixels_per_unit: Option<f32>,
+}
+
+impl ImportedOptions {
+    pub fn to_cbor(&self) -> Result<Vec<u8>, NinePatchError> {
+        let mut buffer = Vec::new();
+        ciborium::into_writer(self, &mut buffer)
+            .map_err(|e| NinePatchError::InvalidFormat(format!("Failed to encode options: {}", e)))?;
+        Ok(buffer)
+    }
+}
+
+// Rectangle in source image pixels, including the 1-pixel marker border unless
+// the image is sliced with explicit insets
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SourceRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    // Stored rotated by 90 degrees counter-clockwise, as texture packers do
+    #[serde(default)]
+    pub rotated: bool,
+}
+
+// Distances from the edges of the image, in pixels
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Insets {
+    pub left: u32,
+    pub top: u32,
+    pub right: u32,
+    pub bottom: u32,
+}
+
+// One toggle per side of the nine-patch
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct Edges {
+    pub left: bool,
+    pub top: bool,
+    pub right: bool,
+    pub bottom: bool,
+}
+
+impl Default for Edges {
+    fn default() -> Self {
+        Edges {
+            left: true,
+            top: true,
+            right: true,
+            bottom: true,
+        }
+    }
+}
+
+// Fill mode of the stretched sections along each axis
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Repeat {
+    pub horizontal: TileMode,
+    pub vertical: TileMode,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TileMode {
+    // Scale the section to fill the space
+    #[default]
+    Stretch,
+    // Repeat the section from the start, cutting off the last tile
+    Tile,
+    // Repeat the section around a centered tile, cutting off both ends
+    TileCenter,
+    // Repeat a whole number of tiles, scaled to fill the space
+    TileFit,
+    // Repeat a whole number of tiles with even gaps between them
+    Space,
+}
+
+// RGBA color, given as a hex string such as "#f80", "#ff8800" or "#ff880080"
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Color(pub Rgba<u8>);
+
+impl TryFrom<String> for Color {
+    type Error = NinePatchError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let invalid = || NinePatchError::InvalidFormat(format!("Invalid color: {}", value));
+
+        let hex = value.strip_prefix('#').unwrap_or(&value);
+        // `from_str_radix` would also take a sign, as in "#+f+f+f"
+        if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(invalid());
+        }
+
+        let digits: Vec<u8> = match hex.len() {
+            // Short forms repeat every digit: "#f80" is "#ff8800"
+            3 | 4 => hex.chars().flat_map(|c| [c, c]).collect::<String>().into_bytes(),
+            6 | 8 => hex.as_bytes().to_vec(),
+            _ => return Err(invalid()),
+        };
+
+        let mut channels = [255u8; 4];
+        for (channel, pair) in channels.iter_mut().zip(digits.chunks(2)) {
+            let pair = std::str::from_utf8(pair).map_err(|_| invalid())?;
+            *channel = u8::from_str_radix(pair, 16).map_err(|_| invalid())?;
+        }
+
+        Ok(Color(Rgba(channels)))
+    }
+}
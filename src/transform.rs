@@ -0,0 +1,84 @@
+use image::imageops;
+
+use crate::options::RenderOptions;
+use crate::{ContentInfo, NinePatch, NinePatchError};
+
+// Flip and rotate a nine-patch before scaling. The stretch and padding
+// metadata follow the content, so the stretch axes rotate with the image.
+pub fn apply_transforms(patch: NinePatch, options: &RenderOptions) -> Result<NinePatch, NinePatchError> {
+    let quarter_turns = match options.rotate {
+        0 => 0,
+        90 => 1,
+        180 => 2,
+        270 => 3,
+        angle => {
+            return Err(NinePatchError::InvalidFormat(format!(
+                "Rotation must be 0, 90, 180 or 270 degrees, got {}",
+                angle
+            )))
+        }
+    };
+
+    let mut patch = patch;
+
+    if options.flip_horizontal {
+        patch = flip_horizontal(patch);
+    }
+    if options.flip_vertical {
+        patch = flip_vertical(patch);
+    }
+    for _ in 0..quarter_turns {
+        patch = rotate_clockwise(patch);
+    }
+
+    Ok(patch)
+}
+
+fn flip_horizontal(patch: NinePatch) -> NinePatch {
+    let width = patch.content.width();
+    let padding = &patch.padding;
+
+    NinePatch {
+        content: imageops::flip_horizontal(&patch.content),
+        grid: patch.grid.flip_horizontal(width),
+        padding: ContentInfo {
+            content_left: padding.content_right,
+            content_top: padding.content_top,
+            content_right: padding.content_left,
+            content_bottom: padding.content_bottom,
+        },
+    }
+}
+
+fn flip_vertical(patch: NinePatch) -> NinePatch {
+    let height = patch.content.height();
+    let padding = &patch.padding;
+
+    NinePatch {
+        content: imageops::flip_vertical(&patch.content),
+        grid: patch.grid.flip_vertical(height),
+        padding: ContentInfo {
+            content_left: padding.content_left,
+            content_top: padding.content_bottom,
+            content_right: padding.content_right,
+            content_bottom: padding.content_top,
+        },
+    }
+}
+
+// Rotate by 90 degrees: the left edge becomes the top edge
+fn rotate_clockwise(patch: NinePatch) -> NinePatch {
+    let height = patch.content.height();
+    let padding = &patch.padding;
+
+    NinePatch {
+        content: imageops::rotate90(&patch.content),
+        grid: patch.grid.rotate_clockwise(height),
+        padding: ContentInfo {
+            content_left: padding.content_bottom,
+            content_top: padding.content_left,
+            content_right: padding.content_top,
+            content_bottom: padding.content_right,
+        },
+    }
+}
@@ -0,0 +1,259 @@
+use crate::options::{ImportedOptions, Insets, SourceRect};
+use crate::NinePatchError;
+
+// Unity's default sprite import density
+const DEFAULT_PIXELS_PER_UNIT: f32 = 100.0;
+
+// Resolve the 9-slice border of a sprite from its `.meta` file. Without a
+// sprite name the whole texture is used with the importer's `spriteBorder`,
+// otherwise the named sprite of the sprite sheet.
+pub fn sprite_options(
+    meta_text: &str,
+    sprite_name: &str,
+    texture_width: u32,
+    texture_height: u32,
+) -> Result<ImportedOptions, NinePatchError> {
+    let root = parse_yaml(meta_text);
+    let importer = root
+        .get("TextureImporter")
+        .ok_or_else(|| NinePatchError::InvalidFormat("Meta file has no TextureImporter".to_string()))?;
+
+    let pixels_per_unit = match importer.get("spritePixelsToUnits") {
+        Some(value) => value.as_f32("spritePixelsToUnits")?,
+        None => DEFAULT_PIXELS_PER_UNIT,
+    };
+    if pixels_per_unit <= 0.0 {
+        return Err(NinePatchError::InvalidFormat(format!("Invalid pixels per unit: {}", pixels_per_unit)));
+    }
+
+    let (source, border) = if sprite_name.is_empty() {
+        (None, importer.get("spriteBorder"))
+    } else {
+        let sprite = importer
+            .get("spriteSheet")
+            .and_then(|sheet| sheet.get("sprites"))
+            .and_then(|sprites| sprites.as_list())
+            .unwrap_or_default()
+            .iter()
+            .find(|sprite| sprite.get("name").and_then(Yaml::as_str) == Some(sprite_name))
+            .ok_or_else(|| NinePatchError::InvalidFormat(format!("Meta file has no sprite named {}", sprite_name)))?;
+
+        let rect = sprite
+            .get("rect")
+            .ok_or_else(|| NinePatchError::InvalidFormat(format!("Sprite {} has no rect", sprite_name)))?;
+        let x = rect.pixels("x")?;
+        let y = rect.pixels("y")?;
+        let width = rect.pixels("width")?;
+        let height = rect.pixels("height")?;
+
+        // Unity measures sprite rects from the bottom-left corner of the texture
+        let top = texture_height.checked_sub(y.saturating_add(height)).ok_or_else(|| {
+            NinePatchError::InvalidFormat(format!("Sprite {} exceeds the {}x{} texture", sprite_name, texture_width, texture_height))
+        })?;
+
+        (Some(SourceRect { x, y: top, width, height, rotated: false }), sprite.get("border"))
+    };
+
+    // The border vector holds left, bottom, right, top
+    let slice = match border {
+        Some(border) => Insets {
+            left: border.pixels("x")?,
+            top: border.pixels("w")?,
+            right: border.pixels("z")?,
+            bottom: border.pixels("y")?,
+        },
+        None => Insets::default(),
+    };
+
+    Ok(ImportedOptions {
+        source,
+        slice: Some(slice),
+        padding: Some(slice),
+        pixels_per_unit: Some(pixels_per_unit),
+        ..Default::default()
+    })
+}
+
+// The YAML subset Unity writes: block maps, block lists, flow maps and scalars
+#[derive(Debug)]
+enum Yaml {
+    Map(Vec<(String, Yaml)>),
+    List(Vec<Yaml>),
+    Scalar(String),
+}
+
+impl Yaml {
+    fn get(&self, key: &str) -> Option<&Yaml> {
+        match self {
+            Yaml::Map(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    fn as_list(&self) -> Option<&[Yaml]> {
+        match self {
+            Yaml::List(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        match self {
+            Yaml::Scalar(value) => Some(value.trim_matches(|c| c == '"' || c == '\'')),
+            _ => None,
+        }
+    }
+
+    fn as_f32(&self, key: &str) -> Result<f32, NinePatchError> {
+        self.as_str()
+            .and_then(|value| value.parse().ok())
+            .ok_or_else(|| NinePatchError::InvalidFormat(format!("Invalid number for {}", key)))
+    }
+
+    // A non-negative pixel value of a map entry, rounded from Unity's floats
+    fn pixels(&self, key: &str) -> Result<u32, NinePatchError> {
+        let value = self
+            .get(key)
+            .ok_or_else(|| NinePatchError::InvalidFormat(format!("Missing field {}", key)))?
+            .as_f32(key)?;
+        Ok(value.max(0.0).round() as u32)
+    }
+}
+
+struct Line<'a> {
+    indent: usize,
+    text: &'a str,
+}
+
+fn parse_yaml(text: &str) -> Yaml {
+    let mut lines: Vec<Line> = text
+        .lines()
+        .filter(|line| {
+            let trimmed = line.trim();
+            !trimmed.is_empty() && !trimmed.starts_with('#') && !trimmed.starts_with('%') && !trimmed.starts_with("---")
+        })
+        .map(|line| Line {
+            indent: line.len() - line.trim_start().len(),
+            text: line.trim(),
+        })
+        .collect();
+
+    let mut index = 0;
+    let indent = lines.first().map_or(0, |line| line.indent);
+    parse_block(&mut lines, &mut index, indent)
+}
+
+fn parse_block(lines: &mut [Line], index: &mut usize, indent: usize) -> Yaml {
+    if *index < lines.len() && is_list_item(&lines[*index]) {
+        parse_list(lines, index, indent)
+    } else {
+        parse_map(lines, index, indent)
+    }
+}
+
+fn parse_map(lines: &mut [Line], index: &mut usize, indent: usize) -> Yaml {
+    let mut entries = Vec::new();
+
+    while *index < lines.len() {
+        let line = &lines[*index];
+        if line.indent < indent || (line.indent == indent && is_list_item(line)) {
+            break;
+        }
+        if line.indent > indent {
+            // Continuation of a multi-line scalar
+            *index += 1;
+            continue;
+        }
+
+        let (key, rest) = line.text.split_once(':').unwrap_or((line.text, ""));
+        let key = key.trim().to_string();
+        let rest = rest.trim();
+        *index += 1;
+
+        let value = if !rest.is_empty() {
+            parse_inline(rest)
+        } else if *index < lines.len() && lines[*index].indent > indent {
+            let child_indent = lines[*index].indent;
+            parse_block(lines, index, child_indent)
+        } else if *index < lines.len() && lines[*index].indent == indent && is_list_item(&lines[*index]) {
+            // Unity writes list items at the indentation of their key
+            parse_list(lines, index, indent)
+        } else {
+            Yaml::Scalar(String::new())
+        };
+
+        entries.push((key, value));
+    }
+
+    Yaml::Map(entries)
+}
+
+fn parse_list(lines: &mut [Line], index: &mut usize, indent: usize) -> Yaml {
+    let mut items = Vec::new();
+
+    while *index < lines.len() && lines[*index].indent == indent && is_list_item(&lines[*index]) {
+        let item = lines[*index].text[1..].trim_start();
+
+        if item.is_empty() || !item.contains(':') || item.starts_with('{') {
+            items.push(parse_inline(item));
+            *index += 1;
+            continue;
+        }
+
+        // The first entry of a map item shares the line with the dash
+        let line = &mut lines[*index];
+        line.text = item;
+        line.indent = indent + 2;
+        items.push(parse_map(lines, index, indent + 2));
+    }
+
+    Yaml::List(items)
+}
+
+fn is_list_item(line: &Line) -> bool {
+    line.text == "-" || line.text.starts_with("- ")
+}
+
+// Flow maps such as `{x: 4, y: 4, z: 4, w: 4}`, empty flow lists and scalars
+fn parse_inline(text: &str) -> Yaml {
+    if let Some(inner) = text.strip_prefix('{').and_then(|t| t.strip_suffix('}')) {
+        let entries = split_flow(inner)
+            .into_iter()
+            .filter_map(|entry| entry.split_once(':'))
+            .map(|(key, value)| (key.trim().to_string(), parse_inline(value.trim())))
+            .collect();
+        return Yaml::Map(entries);
+    }
+
+    if let Some(inner) = text.strip_prefix('[').and_then(|t| t.strip_suffix(']')) {
+        let items = split_flow(inner).into_iter().map(|item| parse_inline(item.trim())).collect();
+        return Yaml::List(items);
+    }
+
+    Yaml::Scalar(text.to_string())
+}
+
+// Split a flow collection at the commas outside of nested braces and brackets
+fn split_flow(text: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+
+    for (i, c) in text.char_indices() {
+        match c {
+            '{' | '[' => depth += 1,
+            '}' | ']' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(&text[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+
+    if !text[start..].trim().is_empty() {
+        parts.push(&text[start..]);
+    }
+
+    parts
+}
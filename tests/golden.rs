@@ -0,0 +1,161 @@
+use nine_patch::{nine_patch_fractional, nine_patch_with_options};
+use image::{ImageBuffer, ImageFormat, Rgba, RgbaImage};
+use ciborium::{cbor, Value};
+use std::path::{Path, PathBuf};
+
+// Renders compared pixel for pixel with the expected images in
+// `tests/fixtures/golden/expected`. Run with `BLESS_GOLDEN=1` to write the
+// current renders as the new expected images.
+
+struct Case {
+    name: &'static str,
+    input: &'static str,
+    width: f32,
+    height: f32,
+    options: Value,
+}
+
+fn case(name: &'static str, input: &'static str, width: f32, height: f32, options: Value) -> Case {
+    Case { name, input, width, height, options }
+}
+
+fn cases() -> Vec<Case> {
+    let none = || cbor!({}).unwrap();
+    let repeat = |mode: &str| cbor!({ "repeat" => { "horizontal" => mode, "vertical" => mode } }).unwrap();
+
+    vec![
+        case("bubble-natural", "docs/bubble.9.png", 510.0, 332.0, none()),
+        case("bubble-wide", "docs/bubble.9.png", 720.0, 360.0, none()),
+        case("bubble-tint", "docs/bubble.9.png", 600.0, 400.0, cbor!({ "tint" => "#3366cc" }).unwrap()),
+        case("checker-stretch", "checker.9.png", 31.0, 23.0, none()),
+        case("checker-tile", "checker.9.png", 31.0, 23.0, repeat("tile")),
+        case("checker-tile-center", "checker.9.png", 31.0, 23.0, repeat("tile-center")),
+        case("checker-tile-fit", "checker.9.png", 31.0, 23.0, repeat("tile-fit")),
+        case("checker-space", "checker.9.png", 31.0, 23.0, repeat("space")),
+        case("checker-fractional", "checker.9.png", 25.5, 17.25, none()),
+        case("checker-rotate-flip", "checker.9.png", 20.0, 30.0, cbor!({ "rotate" => 90, "flip-horizontal" => true }).unwrap()),
+        case("checker-gradient-map", "checker.9.png", 24.0, 16.0, cbor!({ "gradient-map" => ["#000040", "#40c0ff", "#ffffff"] }).unwrap()),
+        case("checker-pin", "checker.9.png", 40.0, 20.0, cbor!({ "pin" => [{ "column" => 3, "size" => 4 }] }).unwrap()),
+        case("frame-stretch", "frame.9.png", 48.0, 30.0, none()),
+        case("frame-opacity", "frame.9.png", 48.0, 30.0, cbor!({ "opacity" => 0.5, "background" => "#ffffff" }).unwrap()),
+        case("frame-shadow", "frame.9.png", 40.0, 24.0, cbor!({ "shadow" => { "offset-x" => 2, "offset-y" => 3, "blur" => 4, "color" => "#00000080" } }).unwrap()),
+        case("frame-glow", "frame.9.png", 40.0, 24.0, cbor!({ "glow" => { "radius" => 3, "color" => "#ffcc00" } }).unwrap()),
+    ]
+}
+
+fn fixtures() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/golden")
+}
+
+fn render(case: &Case) -> RgbaImage {
+    let manifest = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let input = match case.input {
+        path if path.starts_with("docs/") => manifest.join(path),
+        name => fixtures().join("inputs").join(name),
+    };
+    let image_bytes = std::fs::read(&input).unwrap_or_else(|e| panic!("Failed to read {}: {}", input.display(), e));
+
+    let mut options = Vec::new();
+    ciborium::into_writer(&case.options, &mut options).unwrap();
+
+    // Whole sizes go through the plain renderer, fractional ones through their own
+    let result = if case.width.fract() == 0.0 && case.height.fract() == 0.0 {
+        nine_patch_with_options(&image_bytes, &(case.width as u32).to_le_bytes(), &(case.height as u32).to_le_bytes(), &options)
+    } else {
+        nine_patch_fractional(&image_bytes, &case.width.to_le_bytes(), &case.height.to_le_bytes(), &options)
+    };
+
+    assert!(!result.is_empty(), "{}: rendering failed", case.name);
+    image::load_from_memory(&result).unwrap().to_rgba8()
+}
+
+// Differing pixels are red, brighter for larger differences, over a faded
+// copy of the expected image
+fn diff_image(expected: &RgbaImage, actual: &RgbaImage) -> RgbaImage {
+    ImageBuffer::from_fn(expected.width(), expected.height(), |x, y| {
+        let (e, a) = (expected.get_pixel(x, y), actual.get_pixel(x, y));
+        let difference = (0..4).map(|c| e[c].abs_diff(a[c])).max().unwrap();
+
+        if difference == 0 {
+            let gray = ((e[0] as u32 + e[1] as u32 + e[2] as u32) / 3 * e[3] as u32 / 255) as u8;
+            Rgba([gray / 4 + 160, gray / 4 + 160, gray / 4 + 160, 255])
+        } else {
+            Rgba([128 + difference / 2, 0, 0, 255])
+        }
+    })
+}
+
+// A description of how the images differ, or `None` when they are identical
+fn compare(expected: &RgbaImage, actual: &RgbaImage) -> Option<String> {
+    if expected.dimensions() != actual.dimensions() {
+        return Some(format!("size is {:?}, expected {:?}", actual.dimensions(), expected.dimensions()));
+    }
+
+    let differences: Vec<(u32, u32, Rgba<u8>, Rgba<u8>)> = expected
+        .enumerate_pixels()
+        .filter(|&(x, y, e)| e != actual.get_pixel(x, y))
+        .map(|(x, y, e)| (x, y, *e, *actual.get_pixel(x, y)))
+        .collect();
+
+    let &(x, y, e, a) = differences.first()?;
+    let max = differences
+        .iter()
+        .flat_map(|(_, _, e, a)| (0..4).map(move |c| e[c].abs_diff(a[c])))
+        .max()
+        .unwrap_or(0);
+
+    Some(format!(
+        "{} of {} pixels differ by up to {}, first at ({}, {}): expected {:?}, got {:?}",
+        differences.len(),
+        expected.width() * expected.height(),
+        max,
+        x,
+        y,
+        e.0,
+        a.0
+    ))
+}
+
+#[test]
+fn golden_images() {
+    let bless = std::env::var_os("BLESS_GOLDEN").is_some_and(|value| value != "0");
+    let diffs = Path::new(env!("CARGO_TARGET_TMPDIR")).join("golden");
+    let mut failures = Vec::new();
+
+    for case in cases() {
+        let actual = render(&case);
+        let expected_path = fixtures().join("expected").join(format!("{}.png", case.name));
+
+        if bless {
+            actual.save_with_format(&expected_path, ImageFormat::Png).unwrap();
+            continue;
+        }
+
+        let Ok(expected) = image::open(&expected_path) else {
+            failures.push(format!("{}: no expected image at {}", case.name, expected_path.display()));
+            continue;
+        };
+        let expected = expected.to_rgba8();
+
+        if let Some(report) = compare(&expected, &actual) {
+            std::fs::create_dir_all(&diffs).unwrap();
+            let actual_path = diffs.join(format!("{}.actual.png", case.name));
+            actual.save_with_format(&actual_path, ImageFormat::Png).unwrap();
+
+            let mut report = format!("{}: {}\n    actual: {}", case.name, report, actual_path.display());
+            if expected.dimensions() == actual.dimensions() {
+                let diff_path = diffs.join(format!("{}.diff.png", case.name));
+                diff_image(&expected, &actual).save_with_format(&diff_path, ImageFormat::Png).unwrap();
+                report.push_str(&format!("\n    diff: {}", diff_path.display()));
+            }
+            failures.push(report);
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "{} golden images differ, rerun with BLESS_GOLDEN=1 to accept the changes:\n{}",
+        failures.len(),
+        failures.join("\n")
+    );
+}
@@ -1,6 +1,33 @@
-use nine_patch::{nine_patch, nine_patch_content_info};
-use image::{ImageBuffer, Rgba, ImageFormat};
+use nine_patch::{animation_info, atlas_region, configure_cache, configure_limits, css_border_image, generate_nine_patch, godot_style_box, info_handle, load, nine_patch, nine_patch_animated, nine_patch_animated_fractional, nine_patch_batch, nine_patch_content_info, nine_patch_content_info_with_options, nine_patch_fractional, nine_patch_frame, nine_patch_frame_fractional, nine_patch_grid, nine_patch_slices, nine_patch_with_options, render_handle, unity_sprite};
+use image::codecs::gif::{GifDecoder, GifEncoder};
+use image::codecs::png::PngDecoder;
+use image::{AnimationDecoder, Delay, Frame, ImageBuffer, Rgba, ImageFormat};
 use std::io::Cursor;
+use ciborium::{cbor, Value};
+
+// Encode render options the way Typst's `cbor.encode` does
+fn encode_options(options: Value) -> Vec<u8> {
+    let mut data = Vec::new();
+    ciborium::into_writer(&options, &mut data).unwrap();
+    data
+}
+
+// A white nine-patch of the given size, marker border included, that
+// stretches its middle column and row
+fn marked_png(width: u32, height: u32) -> Vec<u8> {
+    let mut img: ImageBuffer<Rgba<u8>, Vec<u8>> = ImageBuffer::from_pixel(width, height, Rgba([0, 0, 0, 0]));
+    img.put_pixel(width / 2, 0, Rgba([0, 0, 0, 255]));
+    img.put_pixel(0, height / 2, Rgba([0, 0, 0, 255]));
+    for y in 1..height - 1 {
+        for x in 1..width - 1 {
+            img.put_pixel(x, y, Rgba([255, 255, 255, 255]));
+        }
+    }
+    
+    let mut png_data = Vec::new();
+    img.write_to(&mut Cursor::new(&mut png_data), ImageFormat::Png).unwrap();
+    png_data
+}
 
 #[test]
 fn test_nine_patch_basic() {
@@ -166,3 +193,1203 @@ fn test_nine_patch_content_info() {
     assert_eq!(min_width, 3, "Minimum width should be 3 (left_fixed + right_fixed = 1 + 2)");
     assert_eq!(min_height, 3, "Minimum height should be 3 (top_fixed + bottom_fixed = 1 + 2)");
 }
+
+#[test]
+fn test_nine_patch_recolor() {
+    // Create a 5x5 nine-patch with a gray content area and a single red pixel
+    let mut img: ImageBuffer<Rgba<u8>, Vec<u8>> = ImageBuffer::new(5, 5);
+    
+    for y in 1..4 {
+        for x in 1..4 {
+            img.put_pixel(x, y, Rgba([128, 128, 128, 255])); // Gray content
+        }
+    }
+    img.put_pixel(1, 1, Rgba([255, 0, 0, 255])); // Red top-left corner
+    
+    // Stretch the middle row and column
+    img.put_pixel(2, 0, Rgba([0, 0, 0, 255]));
+    img.put_pixel(0, 2, Rgba([0, 0, 0, 255]));
+    
+    let mut png_data = Vec::new();
+    {
+        let mut cursor = Cursor::new(&mut png_data);
+        img.write_to(&mut cursor, ImageFormat::Png).unwrap();
+    }
+    
+    // Swap red for blue, then tint everything half-transparent green
+    let options_data = encode_options(cbor!({
+        "color-swap" => [["#ff0000", "#0000ff"]],
+        "tint" => "#00ff0080",
+    }).unwrap());
+    
+    let result = nine_patch_with_options(&png_data, &6u32.to_le_bytes(), &6u32.to_le_bytes(), &options_data);
+    assert!(!result.is_empty(), "Recolored result should not be empty");
+    
+    let result_img = image::load_from_memory(&result).unwrap().to_rgba8();
+    assert_eq!(result_img.dimensions(), (6, 6));
+    
+    // The swapped corner is blue, which the green tint turns black
+    assert_eq!(*result_img.get_pixel(0, 0), Rgba([0, 0, 0, 128]));
+    // Gray keeps only its green channel
+    assert_eq!(*result_img.get_pixel(3, 3), Rgba([0, 128, 0, 128]));
+    
+    // A gradient map replaces gray by the midpoint of its stops
+    let options_data = encode_options(cbor!({
+        "gradient-map" => ["#000000", "#ff0000"],
+    }).unwrap());
+    
+    let result = nine_patch_with_options(&png_data, &6u32.to_le_bytes(), &6u32.to_le_bytes(), &options_data);
+    let result_img = image::load_from_memory(&result).unwrap().to_rgba8();
+    assert_eq!(*result_img.get_pixel(3, 3), Rgba([128, 0, 0, 255]));
+    
+    // Colors are hex digits only, without signs
+    let options_data = encode_options(cbor!({ "tint" => "#+f+f+f" }).unwrap());
+    assert!(nine_patch_with_options(&png_data, &6u32.to_le_bytes(), &6u32.to_le_bytes(), &options_data).is_empty());
+}
+
+#[test]
+fn test_nine_patch_opacity_background() {
+    // Create a 5x5 nine-patch with an opaque white top row and a transparent rest
+    let mut img: ImageBuffer<Rgba<u8>, Vec<u8>> = ImageBuffer::new(5, 5);
+    
+    for x in 1..4 {
+        img.put_pixel(x, 1, Rgba([255, 255, 255, 255])); // White top row
+    }
+    
+    img.put_pixel(2, 0, Rgba([0, 0, 0, 255])); // Top stretch marker
+    img.put_pixel(0, 2, Rgba([0, 0, 0, 255])); // Left stretch marker
+    
+    let mut png_data = Vec::new();
+    {
+        let mut cursor = Cursor::new(&mut png_data);
+        img.write_to(&mut cursor, ImageFormat::Png).unwrap();
+    }
+    
+    // Half opacity alone keeps the colors and halves the alpha
+    let options_data = encode_options(cbor!({ "opacity" => 0.5 }).unwrap());
+    let result = nine_patch_with_options(&png_data, &8u32.to_le_bytes(), &8u32.to_le_bytes(), &options_data);
+    let result_img = image::load_from_memory(&result).unwrap().to_rgba8();
+    assert_eq!(*result_img.get_pixel(4, 0), Rgba([255, 255, 255, 128]));
+    
+    // Over an opaque black background the result is fully opaque gray
+    let options_data = encode_options(cbor!({ "opacity" => 0.5, "background" => "#000000" }).unwrap());
+    let result = nine_patch_with_options(&png_data, &8u32.to_le_bytes(), &8u32.to_le_bytes(), &options_data);
+    let result_img = image::load_from_memory(&result).unwrap().to_rgba8();
+    assert_eq!(*result_img.get_pixel(4, 0), Rgba([128, 128, 128, 255]));
+    // Transparent areas show the background
+    assert_eq!(*result_img.get_pixel(4, 4), Rgba([0, 0, 0, 255]));
+}
+
+#[test]
+fn test_nine_patch_rotate() {
+    // Create a 7x5 nine-patch whose content is 5x3 with a wide fixed right part
+    let mut img: ImageBuffer<Rgba<u8>, Vec<u8>> = ImageBuffer::new(7, 5);
+    
+    for y in 1..4 {
+        for x in 1..6 {
+            img.put_pixel(x, y, Rgba([255, 255, 255, 255])); // White content
+        }
+    }
+    img.put_pixel(1, 1, Rgba([255, 0, 0, 255])); // Red top-left corner
+    
+    // Stretch content column 1 and row 1: fixed left 1, right 3, top 1, bottom 1
+    img.put_pixel(2, 0, Rgba([0, 0, 0, 255]));
+    img.put_pixel(0, 2, Rgba([0, 0, 0, 255]));
+    
+    // Content padding: full width, first content row only
+    for x in 1..6 {
+        img.put_pixel(x, 4, Rgba([0, 0, 0, 255]));
+    }
+    img.put_pixel(6, 1, Rgba([0, 0, 0, 255]));
+    
+    let mut png_data = Vec::new();
+    {
+        let mut cursor = Cursor::new(&mut png_data);
+        img.write_to(&mut cursor, ImageFormat::Png).unwrap();
+    }
+    
+    let options_data = encode_options(cbor!({ "rotate" => 90 }).unwrap());
+    
+    // Rotating clockwise moves the bottom padding to the left and swaps the minimum size
+    let result = nine_patch_content_info_with_options(&png_data, &options_data);
+    let values: Vec<u32> = result.chunks(4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]])).collect();
+    assert_eq!(values, vec![2, 0, 0, 0, 2, 4, 0, 0, 0, 0]);
+    
+    // The minimum height is now 4, which the unrotated patch would not need
+    let result = nine_patch_with_options(&png_data, &2u32.to_le_bytes(), &3u32.to_le_bytes(), &options_data);
+    assert!(result.is_empty(), "Rotated patch should need a height of 4");
+    
+    // The red corner ends up top-right and keeps its size
+    let result = nine_patch_with_options(&png_data, &10u32.to_le_bytes(), &10u32.to_le_bytes(), &options_data);
+    let result_img = image::load_from_memory(&result).unwrap().to_rgba8();
+    assert_eq!(*result_img.get_pixel(9, 0), Rgba([255, 0, 0, 255]));
+    assert_eq!(*result_img.get_pixel(8, 0), Rgba([255, 255, 255, 255]));
+    assert_eq!(*result_img.get_pixel(9, 1), Rgba([255, 255, 255, 255]));
+    
+    // Flipping horizontally moves it to the top-right without rotating
+    let options_data = encode_options(cbor!({ "flip-horizontal" => true }).unwrap());
+    let result = nine_patch_with_options(&png_data, &10u32.to_le_bytes(), &10u32.to_le_bytes(), &options_data);
+    let result_img = image::load_from_memory(&result).unwrap().to_rgba8();
+    assert_eq!(*result_img.get_pixel(9, 0), Rgba([255, 0, 0, 255]));
+}
+
+#[test]
+fn test_generate_nine_patch() {
+    let params = encode_options(cbor!({
+        "radius" => 4,
+        "border-width" => 2,
+        "fill" => "#ffffff",
+        "stroke" => "#ff0000",
+    }).unwrap());
+    
+    let generated = generate_nine_patch(&params);
+    assert!(!generated.is_empty(), "Generated nine-patch should not be empty");
+    
+    // The frame is 4 + 1 + 4 pixels wide plus the marker border
+    let generated_img = image::load_from_memory(&generated).unwrap().to_rgba8();
+    assert_eq!(generated_img.dimensions(), (11, 11));
+    
+    // Padding defaults to the corner radius, the minimum size to both corners
+    let result = nine_patch_content_info(&generated);
+    let values: Vec<u32> = result.chunks(4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]])).collect();
+    assert_eq!(values, vec![4, 4, 4, 4, 8, 8]);
+    
+    // The generated image scales like any other nine-patch
+    let result = nine_patch(&generated, &40u32.to_le_bytes(), &20u32.to_le_bytes());
+    let result_img = image::load_from_memory(&result).unwrap().to_rgba8();
+    assert_eq!(result_img.dimensions(), (40, 20));
+    assert_eq!(*result_img.get_pixel(0, 0), Rgba([0, 0, 0, 0]), "Rounded corner is transparent");
+    assert_eq!(*result_img.get_pixel(20, 0), Rgba([255, 0, 0, 255]), "Top edge is stroked");
+    assert_eq!(*result_img.get_pixel(20, 10), Rgba([255, 255, 255, 255]), "Center is filled");
+    
+    // A shadow grows the canvas by its blur radius and offset
+    let params = encode_options(cbor!({
+        "radius" => 4,
+        "shadow" => { "offset-x" => 0, "offset-y" => 2, "blur" => 3, "color" => "#00000080" },
+    }).unwrap());
+    let generated_img = image::load_from_memory(&generate_nine_patch(&params)).unwrap().to_rgba8();
+    assert_eq!(generated_img.dimensions(), (1 + 3 + 15 + 3 + 1, 1 + 1 + 19 + 5 + 1));
+    
+    // Huge lengths are rejected rather than overflowing the frame size
+    for params in [
+        cbor!({ "radius" => 3_000_000_000u32 }),
+        cbor!({ "shadow" => { "blur" => 3_000_000_000u32 } }),
+        cbor!({ "shadow" => { "offset-x" => i32::MIN } }),
+        // Within the lengths, but larger than the output limits
+        cbor!({ "radius" => 60_000, "shadow" => { "offset-x" => -60_000, "blur" => 60_000 } }),
+    ] {
+        assert!(generate_nine_patch(&encode_options(params.unwrap())).is_empty());
+    }
+}
+
+#[test]
+fn test_nine_patch_shadow_glow() {
+    // Create a fully opaque white 5x5 nine-patch
+    let mut img: ImageBuffer<Rgba<u8>, Vec<u8>> = ImageBuffer::new(5, 5);
+    
+    for y in 1..4 {
+        for x in 1..4 {
+            img.put_pixel(x, y, Rgba([255, 255, 255, 255]));
+        }
+    }
+    img.put_pixel(2, 0, Rgba([0, 0, 0, 255]));
+    img.put_pixel(0, 2, Rgba([0, 0, 0, 255]));
+    
+    let mut png_data = Vec::new();
+    {
+        let mut cursor = Cursor::new(&mut png_data);
+        img.write_to(&mut cursor, ImageFormat::Png).unwrap();
+    }
+    
+    // A hard shadow offset down-right only grows the canvas on those sides
+    let options_data = encode_options(cbor!({
+        "shadow" => { "offset-x" => 2, "offset-y" => 2, "blur" => 0, "color" => "#000000" },
+    }).unwrap());
+    
+    let result = nine_patch_content_info_with_options(&png_data, &options_data);
+    let outsets: Vec<u32> = result[24..].chunks(4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]])).collect();
+    assert_eq!(outsets, vec![0, 0, 2, 2]);
+    
+    let result = nine_patch_with_options(&png_data, &10u32.to_le_bytes(), &10u32.to_le_bytes(), &options_data);
+    let result_img = image::load_from_memory(&result).unwrap().to_rgba8();
+    assert_eq!(result_img.dimensions(), (12, 12));
+    assert_eq!(*result_img.get_pixel(0, 0), Rgba([255, 255, 255, 255]), "Image stays at the origin");
+    assert_eq!(*result_img.get_pixel(11, 11), Rgba([0, 0, 0, 255]), "Shadow fills the offset corner");
+    assert_eq!(*result_img.get_pixel(11, 0), Rgba([0, 0, 0, 0]), "Shadow is offset downwards");
+    
+    // A glow grows the canvas evenly and fades out
+    let options_data = encode_options(cbor!({
+        "glow" => { "radius" => 3, "color" => "#ff0000" },
+    }).unwrap());
+    let result = nine_patch_with_options(&png_data, &10u32.to_le_bytes(), &10u32.to_le_bytes(), &options_data);
+    let result_img = image::load_from_memory(&result).unwrap().to_rgba8();
+    assert_eq!(result_img.dimensions(), (16, 16));
+    assert_eq!(*result_img.get_pixel(3, 3), Rgba([255, 255, 255, 255]));
+    let near = result_img.get_pixel(2, 8)[3];
+    let far = result_img.get_pixel(0, 8)[3];
+    assert!(near > far && far > 0, "Glow should fade with distance");
+}
+
+#[test]
+fn test_nine_patch_source_region() {
+    // Create a 10x5 sprite sheet holding a red and a blue 5x5 nine-patch
+    let mut img: ImageBuffer<Rgba<u8>, Vec<u8>> = ImageBuffer::new(10, 5);
+    
+    for (offset, color) in [(0, Rgba([255, 0, 0, 255])), (5, Rgba([0, 0, 255, 255]))] {
+        for y in 1..4 {
+            for x in 1..4 {
+                img.put_pixel(offset + x, y, color);
+            }
+        }
+        img.put_pixel(offset + 2, 0, Rgba([0, 0, 0, 255]));
+        img.put_pixel(offset, 2, Rgba([0, 0, 0, 255]));
+    }
+    
+    let mut png_data = Vec::new();
+    {
+        let mut cursor = Cursor::new(&mut png_data);
+        img.write_to(&mut cursor, ImageFormat::Png).unwrap();
+    }
+    
+    let options_data = encode_options(cbor!({
+        "source" => { "x" => 5, "y" => 0, "width" => 5, "height" => 5 },
+    }).unwrap());
+    let result = nine_patch_with_options(&png_data, &8u32.to_le_bytes(), &8u32.to_le_bytes(), &options_data);
+    let result_img = image::load_from_memory(&result).unwrap().to_rgba8();
+    assert_eq!(result_img.dimensions(), (8, 8));
+    assert!(result_img.pixels().all(|p| *p == Rgba([0, 0, 255, 255])), "Only the blue frame should be used");
+    
+    // Regions outside the sheet are rejected
+    let options_data = encode_options(cbor!({
+        "source" => { "x" => 6, "y" => 0, "width" => 5, "height" => 5 },
+    }).unwrap());
+    let result = nine_patch_with_options(&png_data, &8u32.to_le_bytes(), &8u32.to_le_bytes(), &options_data);
+    assert!(result.is_empty(), "Source region outside the image should fail");
+}
+
+#[test]
+fn test_atlas_region() {
+    // A 12x6 page: a red plain region and a 6x6 frame with a green center
+    let mut page: ImageBuffer<Rgba<u8>, Vec<u8>> = ImageBuffer::new(12, 6);
+    
+    for y in 0..6 {
+        for x in 0..6 {
+            page.put_pixel(x, y, Rgba([255, 0, 0, 255]));
+            let inner = (2..4).contains(&x) && (2..4).contains(&y);
+            let color = if inner { Rgba([0, 255, 0, 255]) } else { Rgba([0, 0, 255, 255]) };
+            page.put_pixel(6 + x, y, color);
+        }
+    }
+    
+    let mut png_data = Vec::new();
+    {
+        let mut cursor = Cursor::new(&mut png_data);
+        page.write_to(&mut cursor, ImageFormat::Png).unwrap();
+    }
+    
+    let atlas = "
+page.png
+size: 12, 6
+format: RGBA8888
+filter: Nearest, Nearest
+repeat: none
+plain
+  rotate: false
+  xy: 0, 0
+  size: 6, 6
+  orig: 6, 6
+  offset: 0, 0
+  index: -1
+frame
+  rotate: false
+  xy: 6, 0
+  size: 6, 6
+  split: 2, 2, 2, 2
+  pad: 1, -1, 1, 1
+  orig: 6, 6
+  offset: 0, 0
+  index: -1
+
+second.png
+size: 6, 6
+format: RGBA8888
+filter: Nearest, Nearest
+repeat: none
+other
+  rotate: false
+  xy: 0, 0
+  size: 6, 6
+  orig: 6, 6
+  offset: 0, 0
+  index: -1
+";
+    
+    let options_data = atlas_region(atlas.as_bytes(), b"frame");
+    assert!(!options_data.is_empty(), "Region options should not be empty");
+    
+    // The page names the image each region is cut from
+    let page = |options_data: &[u8]| {
+        let options: Value = ciborium::from_reader(options_data).unwrap();
+        let page = options.as_map().unwrap().iter().find(|(key, _)| key.as_text() == Some("page")).map(|(_, page)| page.clone());
+        page.and_then(|page| page.into_text().ok())
+    };
+    assert_eq!(page(&options_data).as_deref(), Some("page.png"));
+    assert_eq!(page(&atlas_region(atlas.as_bytes(), b"other")).as_deref(), Some("second.png"));
+    
+    // Padding of -1 falls back to the split
+    let result = nine_patch_content_info_with_options(&png_data, &options_data);
+    let values: Vec<u32> = result.chunks(4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]])).collect();
+    assert_eq!(values, vec![1, 1, 2, 1, 4, 4, 0, 0, 0, 0]);
+    
+    // The split keeps the blue border 2 pixels wide and stretches the green center
+    let result = nine_patch_with_options(&png_data, &10u32.to_le_bytes(), &8u32.to_le_bytes(), &options_data);
+    let result_img = image::load_from_memory(&result).unwrap().to_rgba8();
+    assert_eq!(result_img.dimensions(), (10, 8));
+    assert_eq!(*result_img.get_pixel(1, 4), Rgba([0, 0, 255, 255]));
+    assert_eq!(*result_img.get_pixel(2, 2), Rgba([0, 255, 0, 255]));
+    assert_eq!(*result_img.get_pixel(7, 5), Rgba([0, 255, 0, 255]));
+    assert_eq!(*result_img.get_pixel(8, 5), Rgba([0, 0, 255, 255]));
+    
+    // Regions without split scale as a whole
+    let options_data = atlas_region(atlas.as_bytes(), b"plain");
+    let result = nine_patch_with_options(&png_data, &10u32.to_le_bytes(), &8u32.to_le_bytes(), &options_data);
+    let result_img = image::load_from_memory(&result).unwrap().to_rgba8();
+    assert!(result_img.pixels().all(|p| *p == Rgba([255, 0, 0, 255])));
+    
+    // The compact format of newer libGDX versions resolves the same region
+    let compact = "page.png\nsize:12,6\nfilter:Nearest,Nearest\nframe\nbounds:6,0,6,6\nsplit:2,2,2,2\npad:1,-1,1,1\n";
+    assert_eq!(atlas_region(compact.as_bytes(), b"frame"), atlas_region(atlas.as_bytes(), b"frame"));
+    
+    assert!(atlas_region(atlas.as_bytes(), b"missing").is_empty());
+}
+
+#[test]
+fn test_unity_sprite() {
+    // An 8x16 texture: a red sprite on top of a blue sprite with a 2 pixel white border
+    let mut texture: ImageBuffer<Rgba<u8>, Vec<u8>> = ImageBuffer::new(8, 16);
+    
+    for y in 0..16 {
+        for x in 0..8 {
+            let color = if y < 8 {
+                Rgba([255, 0, 0, 255])
+            } else if !(2..6).contains(&x) || !(10..14).contains(&y) {
+                Rgba([255, 255, 255, 255])
+            } else {
+                Rgba([0, 0, 255, 255])
+            };
+            texture.put_pixel(x, y, color);
+        }
+    }
+    
+    let mut png_data = Vec::new();
+    {
+        let mut cursor = Cursor::new(&mut png_data);
+        texture.write_to(&mut cursor, ImageFormat::Png).unwrap();
+    }
+    
+    let meta = "fileFormatVersion: 2
+guid: 0123456789abcdef0123456789abcdef
+TextureImporter:
+  internalIDToNameTable: []
+  serializedVersion: 12
+  spriteMode: 2
+  spriteBorder: {x: 1, y: 1, z: 1, w: 1}
+  spritePixelsToUnits: 16
+  spriteSheet:
+    serializedVersion: 2
+    sprites:
+    - serializedVersion: 2
+      name: frame_top
+      rect:
+        serializedVersion: 2
+        x: 0
+        y: 8
+        width: 8
+        height: 8
+      alignment: 0
+      pivot: {x: 0.5, y: 0.5}
+      border: {x: 0, y: 0, z: 0, w: 0}
+      outline: []
+    - serializedVersion: 2
+      name: frame_bottom
+      rect:
+        serializedVersion: 2
+        x: 0
+        y: 0
+        width: 8
+        height: 8
+      alignment: 0
+      pivot: {x: 0.5, y: 0.5}
+      border: {x: 2, y: 2, z: 2, w: 2}
+      outline: []
+  spritePackingTag: 
+  userData: 
+";
+    
+    let options_data = unity_sprite(&png_data, meta.as_bytes(), b"frame_bottom");
+    assert!(!options_data.is_empty(), "Sprite options should not be empty");
+    
+    // Pixels per unit are returned for the Typst side to turn into a scale
+    let options: Value = ciborium::from_reader(options_data.as_slice()).unwrap();
+    let pixels_per_unit = options.as_map().unwrap().iter()
+        .find(|(k, _)| k.as_text() == Some("pixels-per-unit"))
+        .and_then(|(_, v)| v.as_float());
+    assert_eq!(pixels_per_unit, Some(16.0));
+    
+    // The bottom sprite sits in the lower half of the texture, sliced at its border
+    let result = nine_patch_with_options(&png_data, &12u32.to_le_bytes(), &12u32.to_le_bytes(), &options_data);
+    let result_img = image::load_from_memory(&result).unwrap().to_rgba8();
+    assert_eq!(*result_img.get_pixel(1, 6), Rgba([255, 255, 255, 255]));
+    assert_eq!(*result_img.get_pixel(6, 6), Rgba([0, 0, 255, 255]));
+    assert_eq!(*result_img.get_pixel(10, 10), Rgba([255, 255, 255, 255]));
+    
+    let result = nine_patch_content_info_with_options(&png_data, &options_data);
+    let values: Vec<u32> = result.chunks(4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]])).collect();
+    assert_eq!(&values[..6], &[2, 2, 2, 2, 4, 4]);
+    
+    // Without a name the whole texture is sliced at the importer's border
+    let options_data = unity_sprite(&png_data, meta.as_bytes(), b"");
+    let result = nine_patch_content_info_with_options(&png_data, &options_data);
+    let values: Vec<u32> = result.chunks(4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]])).collect();
+    assert_eq!(&values[..6], &[1, 1, 1, 1, 2, 2]);
+    
+    assert!(unity_sprite(&png_data, meta.as_bytes(), b"missing").is_empty());
+}
+
+#[test]
+fn test_godot_style_box() {
+    // A 6x6 texture with a 2 pixel blue border and a green/yellow striped center
+    let mut texture: ImageBuffer<Rgba<u8>, Vec<u8>> = ImageBuffer::new(6, 6);
+    
+    for y in 0..6 {
+        for x in 0..6 {
+            let color = if !(2..4).contains(&x) || !(2..4).contains(&y) {
+                Rgba([0, 0, 255, 255])
+            } else if x == 2 {
+                Rgba([0, 255, 0, 255])
+            } else {
+                Rgba([255, 255, 0, 255])
+            };
+            texture.put_pixel(x, y, color);
+        }
+    }
+    
+    let mut png_data = Vec::new();
+    {
+        let mut cursor = Cursor::new(&mut png_data);
+        texture.write_to(&mut cursor, ImageFormat::Png).unwrap();
+    }
+    
+    let resource = r#"[gd_resource type="StyleBoxTexture" load_steps=2 format=3 uid="uid://b6x3ngf2r1k0a"]
+
+[ext_resource type="Texture2D" uid="uid://c1v2pgq0fw2dn" path="res://frame.png" id="1_frame"]
+
+[resource]
+content_margin_left = 3.0
+texture = ExtResource("1_frame")
+texture_margin_left = 2.0
+texture_margin_top = 2.0
+texture_margin_right = 2.0
+texture_margin_bottom = 2.0
+expand_margin_left = 1.0
+axis_stretch_horizontal = 1
+region_rect = Rect2(0, 0, 6, 6)
+"#;
+    
+    let options_data = godot_style_box(resource.as_bytes());
+    assert!(!options_data.is_empty(), "Style box options should not be empty");
+    
+    // The expand margin is drawn outside the target and lowers the minimum width
+    let result = nine_patch_content_info_with_options(&png_data, &options_data);
+    let values: Vec<u32> = result.chunks(4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]])).collect();
+    assert_eq!(values, vec![3, 2, 2, 2, 3, 4, 1, 0, 0, 0]);
+    
+    let result = nine_patch_with_options(&png_data, &10u32.to_le_bytes(), &6u32.to_le_bytes(), &options_data);
+    let result_img = image::load_from_memory(&result).unwrap().to_rgba8();
+    assert_eq!(result_img.dimensions(), (11, 6));
+    
+    // Tiling repeats the stripes instead of widening them
+    let row: Vec<Rgba<u8>> = (2..9).map(|x| *result_img.get_pixel(x, 3)).collect();
+    let green = Rgba([0, 255, 0, 255]);
+    let yellow = Rgba([255, 255, 0, 255]);
+    assert_eq!(row, vec![green, yellow, green, yellow, green, yellow, green]);
+    
+    // Tile-fit squeezes a whole number of tiles, here three, into the five center pixels
+    let resource = "[resource]\ntexture_margin_left = 2.0\ntexture_margin_top = 2.0\ntexture_margin_right = 2.0\ntexture_margin_bottom = 2.0\naxis_stretch_horizontal = 2\n";
+    let options_data = godot_style_box(resource.as_bytes());
+    let result = nine_patch_with_options(&png_data, &9u32.to_le_bytes(), &6u32.to_le_bytes(), &options_data);
+    let result_img = image::load_from_memory(&result).unwrap().to_rgba8();
+    let row: Vec<Rgba<u8>> = (2..7).map(|x| *result_img.get_pixel(x, 3)).collect();
+    assert_eq!(row, vec![green, yellow, green, yellow, green]);
+    
+    // Without its center the style box keeps only the border
+    let resource = "[resource]\ntexture_margin_left = 2.0\ntexture_margin_top = 2.0\ntexture_margin_right = 2.0\ntexture_margin_bottom = 2.0\ndraw_center = false\n";
+    let options_data = godot_style_box(resource.as_bytes());
+    let result = nine_patch_with_options(&png_data, &9u32.to_le_bytes(), &6u32.to_le_bytes(), &options_data);
+    let result_img = image::load_from_memory(&result).unwrap().to_rgba8();
+    assert_eq!(*result_img.get_pixel(4, 3), Rgba([0, 0, 0, 0]));
+    assert_eq!(*result_img.get_pixel(1, 3), Rgba([0, 0, 255, 255]));
+}
+
+#[test]
+fn test_css_border_image() {
+    // A 6x6 image with a 2 pixel blue border and a green center
+    let mut source: ImageBuffer<Rgba<u8>, Vec<u8>> = ImageBuffer::new(6, 6);
+    
+    for y in 0..6 {
+        for x in 0..6 {
+            let color = if !(2..4).contains(&x) || !(2..4).contains(&y) {
+                Rgba([0, 0, 255, 255])
+            } else {
+                Rgba([0, 255, 0, 255])
+            };
+            source.put_pixel(x, y, color);
+        }
+    }
+    
+    let mut png_data = Vec::new();
+    {
+        let mut cursor = Cursor::new(&mut png_data);
+        source.write_to(&mut cursor, ImageFormat::Png).unwrap();
+    }
+    
+    // Without `fill` the center stays transparent, the border is drawn 4 pixels wide
+    let css = ".frame { border-image: url(\"frame.png\") 2 / 4px round; /* no fill */ }";
+    let options_data = css_border_image(&png_data, css.as_bytes());
+    assert!(!options_data.is_empty(), "Border image options should not be empty");
+    
+    let result = nine_patch_content_info_with_options(&png_data, &options_data);
+    let values: Vec<u32> = result.chunks(4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]])).collect();
+    assert_eq!(values, vec![4, 4, 4, 4, 8, 8, 0, 0, 0, 0]);
+    
+    let result = nine_patch_with_options(&png_data, &12u32.to_le_bytes(), &12u32.to_le_bytes(), &options_data);
+    let result_img = image::load_from_memory(&result).unwrap().to_rgba8();
+    assert_eq!(result_img.dimensions(), (12, 12));
+    assert_eq!(*result_img.get_pixel(3, 6), Rgba([0, 0, 255, 255]));
+    assert_eq!(*result_img.get_pixel(6, 3), Rgba([0, 0, 255, 255]));
+    assert_eq!(result_img.get_pixel(6, 6)[3], 0);
+    
+    // Longhands: percentages resolve against the image, outset numbers multiply the width
+    let css = "border-image-source: url(frame.png); border-image-slice: 33.4% fill; border-image-outset: 1";
+    let options_data = css_border_image(&png_data, css.as_bytes());
+    let result = nine_patch_content_info_with_options(&png_data, &options_data);
+    let values: Vec<u32> = result.chunks(4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]])).collect();
+    assert_eq!(values[..4], [2, 2, 2, 2]);
+    assert_eq!(values[6..], [2, 2, 2, 2]);
+    
+    let result = nine_patch_with_options(&png_data, &6u32.to_le_bytes(), &6u32.to_le_bytes(), &options_data);
+    let result_img = image::load_from_memory(&result).unwrap().to_rgba8();
+    assert_eq!(result_img.dimensions(), (10, 10));
+    assert_eq!(*result_img.get_pixel(5, 5), Rgba([0, 255, 0, 255]));
+    
+    // Number widths and outsets multiply the element's border width
+    let css = "border-width: 3px; border-image: url(frame.png) 2 / 2 / 1";
+    let options_data = css_border_image(&png_data, css.as_bytes());
+    let result = nine_patch_content_info_with_options(&png_data, &options_data);
+    let values: Vec<u32> = result.chunks(4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]])).collect();
+    assert_eq!(values, vec![6, 6, 6, 6, 6, 6, 3, 3, 3, 3]);
+    
+    // Lengths other than pixels cannot be resolved
+    assert!(css_border_image(&png_data, b"border-image-width: 2em").is_empty());
+    assert!(css_border_image(&png_data, b"border-width: thin; border-image-slice: 2").is_empty());
+}
+
+#[test]
+fn test_draw_edges() {
+    // A solid red 6x6 image sliced 2 pixels from each side
+    let source: ImageBuffer<Rgba<u8>, Vec<u8>> = ImageBuffer::from_pixel(6, 6, Rgba([255, 0, 0, 255]));
+    
+    let mut png_data = Vec::new();
+    {
+        let mut cursor = Cursor::new(&mut png_data);
+        source.write_to(&mut cursor, ImageFormat::Png).unwrap();
+    }
+    
+    // Only the top and bottom rules, including their corners
+    let options_data = encode_options(cbor!({
+        "slice" => { "left" => 2, "top" => 2, "right" => 2, "bottom" => 2 },
+        "draw-center" => false,
+        "draw-edges" => { "left" => false, "right" => false },
+    }).unwrap());
+    
+    let result = nine_patch_with_options(&png_data, &10u32.to_le_bytes(), &10u32.to_le_bytes(), &options_data);
+    let result_img = image::load_from_memory(&result).unwrap().to_rgba8();
+    
+    for (x, y, pixel) in result_img.enumerate_pixels() {
+        let drawn = !(2..8).contains(&y);
+        assert_eq!(pixel[3] == 255, drawn, "Unexpected alpha at ({}, {})", x, y);
+    }
+    
+    // Hiding every side keeps only the center
+    let options_data = encode_options(cbor!({
+        "slice" => { "left" => 2, "top" => 2, "right" => 2, "bottom" => 2 },
+        "draw-edges" => { "left" => false, "top" => false, "right" => false, "bottom" => false },
+    }).unwrap());
+    
+    let result = nine_patch_with_options(&png_data, &10u32.to_le_bytes(), &10u32.to_le_bytes(), &options_data);
+    let result_img = image::load_from_memory(&result).unwrap().to_rgba8();
+    
+    for (x, y, pixel) in result_img.enumerate_pixels() {
+        let drawn = (2..8).contains(&x) && (2..8).contains(&y);
+        assert_eq!(pixel[3] == 255, drawn, "Unexpected alpha at ({}, {})", x, y);
+    }
+}
+
+#[test]
+fn test_nine_patch_slices() {
+    // A 6x6 image, sliced 1 pixel from the left and 2 from the other sides
+    let source: ImageBuffer<Rgba<u8>, Vec<u8>> = ImageBuffer::from_fn(6, 6, |x, _| Rgba([x as u8 * 40, 0, 0, 255]));
+    
+    let mut png_data = Vec::new();
+    {
+        let mut cursor = Cursor::new(&mut png_data);
+        source.write_to(&mut cursor, ImageFormat::Png).unwrap();
+    }
+    
+    let options_data = encode_options(cbor!({
+        "slice" => { "left" => 1, "top" => 2, "right" => 2, "bottom" => 2 },
+        "repeat" => { "horizontal" => "tile" },
+        "draw-center" => false,
+    }).unwrap());
+    
+    let result = nine_patch_slices(&png_data, &options_data);
+    let slices: Value = ciborium::from_reader(result.as_slice()).unwrap();
+    let field = |value: &Value, name: &str| -> Value {
+        value.as_map().unwrap().iter().find(|(key, _)| key.as_text() == Some(name)).unwrap().1.clone()
+    };
+    let sizes = |name: &str| -> Vec<(i128, bool)> {
+        field(&slices, name).as_array().unwrap().iter()
+            .map(|track| (field(track, "size").as_integer().unwrap().into(), field(track, "stretch").as_bool().unwrap()))
+            .collect()
+    };
+    assert_eq!(sizes("columns"), vec![(1, false), (3, true), (2, false)]);
+    assert_eq!(sizes("rows"), vec![(2, false), (2, true), (2, false)]);
+    assert_eq!(field(&field(&slices, "repeat"), "horizontal").as_text(), Some("tile"));
+    
+    // Eight cells without the center, each an unscaled PNG of its source region
+    let cells = field(&slices, "cells");
+    let cells = cells.as_array().unwrap();
+    assert_eq!(cells.len(), 8);
+    
+    let top_edge = cells.iter()
+        .find(|cell| field(cell, "column") == Value::from(1) && field(cell, "row") == Value::from(0))
+        .unwrap();
+    let top_edge = image::load_from_memory(field(top_edge, "image").as_bytes().unwrap()).unwrap().to_rgba8();
+    assert_eq!(top_edge.dimensions(), (3, 2));
+    assert_eq!(*top_edge.get_pixel(0, 0), Rgba([40, 0, 0, 255]));
+    
+    // Effects only exist on the flattened image
+    let options_data = encode_options(cbor!({
+        "slice" => { "left" => 1, "top" => 2, "right" => 2, "bottom" => 2 },
+        "glow" => { "radius" => 2 },
+    }).unwrap());
+    assert!(nine_patch_slices(&png_data, &options_data).is_empty());
+    
+    // Pins and anchors would change the track sizes that the caller lays out
+    let slice = cbor!({ "left" => 1, "top" => 2, "right" => 2, "bottom" => 2 }).unwrap();
+    for options in [
+        cbor!({ "slice" => slice.clone(), "pin" => [{ "column" => 1, "size" => 2 }] }),
+        cbor!({ "slice" => slice.clone(), "anchor" => [{ "column" => 0, "offset" => 2 }] }),
+        // A background would leave hidden cells and tile gaps uncovered
+        cbor!({ "slice" => slice.clone(), "background" => "#ffffff" }),
+    ] {
+        assert!(nine_patch_slices(&png_data, &encode_options(options.unwrap())).is_empty());
+    }
+}
+
+#[test]
+fn test_grid_pins() {
+    // A speech bubble edge: two stretch runs around a 3 pixel red tail
+    let mut img: ImageBuffer<Rgba<u8>, Vec<u8>> = ImageBuffer::new(9, 5);
+    
+    for y in 1..4 {
+        for x in 1..8 {
+            let color = if (3..6).contains(&x) { Rgba([255, 0, 0, 255]) } else { Rgba([255, 255, 255, 255]) };
+            img.put_pixel(x, y, color);
+        }
+    }
+    
+    let black = Rgba([0, 0, 0, 255]);
+    img.put_pixel(2, 0, black);
+    img.put_pixel(6, 0, black);
+    img.put_pixel(0, 2, black);
+    
+    let mut png_data = Vec::new();
+    {
+        let mut cursor = Cursor::new(&mut png_data);
+        img.write_to(&mut cursor, ImageFormat::Png).unwrap();
+    }
+    
+    // The parsed grid alternates fixed and stretch columns
+    let result = nine_patch_grid(&png_data, &[]);
+    let grid: Value = ciborium::from_reader(result.as_slice()).unwrap();
+    let columns: Vec<(u32, u32, bool)> = grid.as_map().unwrap()[0].1.as_array().unwrap().iter()
+        .map(|column| {
+            let field = |name: &str| column.as_map().unwrap().iter().find(|(key, _)| key.as_text() == Some(name)).unwrap().1.clone();
+            (
+                i128::from(field("start").as_integer().unwrap()) as u32,
+                i128::from(field("size").as_integer().unwrap()) as u32,
+                field("stretch").as_bool().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(columns, vec![(0, 1, false), (1, 1, true), (2, 3, false), (5, 1, true), (6, 1, false)]);
+    
+    // Find where the tail starts in a rendered row
+    let tail_start = |options_data: &[u8]| -> u32 {
+        let result = nine_patch_with_options(&png_data, &17u32.to_le_bytes(), &3u32.to_le_bytes(), options_data);
+        let result_img = image::load_from_memory(&result).unwrap().to_rgba8();
+        (0..17).find(|&x| *result_img.get_pixel(x, 1) == Rgba([255, 0, 0, 255])).unwrap()
+    };
+    
+    // Both stretch runs share the extra space evenly by default
+    assert_eq!(tail_start(&[]), 7);
+    
+    // Pinning the tail's offset moves it, a pinned size fixes the run before it
+    let options_data = encode_options(cbor!({ "pin" => [{ "column" => 2, "offset" => "25%" }] }).unwrap());
+    assert_eq!(tail_start(&options_data), 4);
+    let options_data = encode_options(cbor!({ "pin" => [{ "column" => 1, "size" => 2 }] }).unwrap());
+    assert_eq!(tail_start(&options_data), 3);
+    
+    // Fixed columns keep their size
+    let options_data = encode_options(cbor!({ "pin" => [{ "column" => 2, "size" => 5 }] }).unwrap());
+    assert!(nine_patch_with_options(&png_data, &17u32.to_le_bytes(), &3u32.to_le_bytes(), &options_data).is_empty());
+}
+
+#[test]
+fn test_anchor_tail() {
+    // Stretch runs on both sides of a 3 pixel red tail
+    let mut img: ImageBuffer<Rgba<u8>, Vec<u8>> = ImageBuffer::new(9, 5);
+    
+    for y in 1..4 {
+        for x in 1..8 {
+            let color = if (3..6).contains(&x) { Rgba([255, 0, 0, 255]) } else { Rgba([255, 255, 255, 255]) };
+            img.put_pixel(x, y, color);
+        }
+    }
+    
+    let black = Rgba([0, 0, 0, 255]);
+    img.put_pixel(2, 0, black);
+    img.put_pixel(6, 0, black);
+    img.put_pixel(0, 2, black);
+    
+    let mut png_data = Vec::new();
+    {
+        let mut cursor = Cursor::new(&mut png_data);
+        img.write_to(&mut cursor, ImageFormat::Png).unwrap();
+    }
+    
+    let tail_start = |anchor: Value| -> u32 {
+        let options_data = encode_options(cbor!({ "anchor" => [anchor] }).unwrap());
+        let result = nine_patch_with_options(&png_data, &17u32.to_le_bytes(), &3u32.to_le_bytes(), &options_data);
+        let result_img = image::load_from_memory(&result).unwrap().to_rgba8();
+        (0..17).find(|&x| *result_img.get_pixel(x, 1) == Rgba([255, 0, 0, 255])).unwrap()
+    };
+    
+    // The middle of the tail points at the offset by default
+    assert_eq!(tail_start(cbor!({ "column" => 2, "offset" => 12 }).unwrap()), 10);
+    assert_eq!(tail_start(cbor!({ "column" => 2, "offset" => "70%", "point" => 0.0 }).unwrap()), 12);
+    
+    // The tail stays clear of the fixed corner
+    assert_eq!(tail_start(cbor!({ "column" => 2, "offset" => 17 }).unwrap()), 13);
+    assert_eq!(tail_start(cbor!({ "column" => 2, "offset" => 0 }).unwrap()), 1);
+}
+
+#[test]
+fn test_nine_patch_fractional() {
+    // A solid blue 6x6 image sliced 2 pixels from each side
+    let source: ImageBuffer<Rgba<u8>, Vec<u8>> = ImageBuffer::from_pixel(6, 6, Rgba([0, 0, 255, 255]));
+    
+    let mut png_data = Vec::new();
+    {
+        let mut cursor = Cursor::new(&mut png_data);
+        source.write_to(&mut cursor, ImageFormat::Png).unwrap();
+    }
+    
+    let options_data = encode_options(cbor!({
+        "slice" => { "left" => 2, "top" => 2, "right" => 2, "bottom" => 2 },
+    }).unwrap());
+    
+    let result = nine_patch_fractional(&png_data, &10.25f32.to_le_bytes(), &6.5f32.to_le_bytes(), &options_data);
+    assert!(!result.is_empty(), "Result should not be empty");
+    
+    let result_img = image::load_from_memory(&result).unwrap().to_rgba8();
+    assert_eq!(result_img.dimensions(), (11, 7));
+    
+    // The last column and row only cover part of a pixel
+    assert_eq!(result_img.get_pixel(9, 5)[3], 255);
+    assert_eq!(result_img.get_pixel(10, 5)[3], 64);
+    assert_eq!(result_img.get_pixel(9, 6)[3], 128);
+    assert_eq!(result_img.get_pixel(10, 6)[3], 32);
+    
+    // Whole sizes render like `nine_patch_with_options`
+    let fractional = nine_patch_fractional(&png_data, &10f32.to_le_bytes(), &6f32.to_le_bytes(), &options_data);
+    let whole = nine_patch_with_options(&png_data, &10u32.to_le_bytes(), &6u32.to_le_bytes(), &options_data);
+    assert_eq!(fractional, whole);
+    
+    assert!(nine_patch_fractional(&png_data, &f32::NAN.to_le_bytes(), &6f32.to_le_bytes(), &options_data).is_empty());
+}
+
+// A two-frame 5x5 nine-patch, red then blue, with markers only on the first frame
+fn animation_frames() -> Vec<Frame> {
+    let mut frames = Vec::new();
+    
+    for (index, color) in [Rgba([255, 0, 0, 255]), Rgba([0, 0, 255, 255])].into_iter().enumerate() {
+        let mut img: ImageBuffer<Rgba<u8>, Vec<u8>> = ImageBuffer::new(5, 5);
+        for y in 1..4 {
+            for x in 1..4 {
+                img.put_pixel(x, y, color);
+            }
+        }
+        if index == 0 {
+            img.put_pixel(2, 0, Rgba([0, 0, 0, 255]));
+            img.put_pixel(0, 2, Rgba([0, 0, 0, 255]));
+        }
+        
+        let delay = Delay::from_numer_denom_ms(100 * (index as u32 + 1), 1);
+        frames.push(Frame::from_parts(img, 0, 0, delay));
+    }
+    
+    frames
+}
+
+#[test]
+fn test_nine_patch_animated() {
+    let mut gif_data = Vec::new();
+    {
+        let mut encoder = GifEncoder::new(&mut gif_data);
+        encoder.encode_frames(animation_frames()).unwrap();
+    }
+    
+    let result = nine_patch_animated(&gif_data, &8u32.to_le_bytes(), &6u32.to_le_bytes(), &[]);
+    assert!(!result.is_empty(), "Animated result should not be empty");
+    
+    // Every frame is scaled with the markers of the first one, keeping its delay
+    let frames = GifDecoder::new(Cursor::new(&result)).unwrap().into_frames().collect_frames().unwrap();
+    assert_eq!(frames.len(), 2);
+    assert_eq!(frames[0].buffer().dimensions(), (8, 6));
+    assert_eq!(*frames[0].buffer().get_pixel(4, 3), Rgba([255, 0, 0, 255]));
+    assert_eq!(*frames[1].buffer().get_pixel(4, 3), Rgba([0, 0, 255, 255]));
+    assert_eq!(frames[1].delay().numer_denom_ms(), (200, 1));
+    
+    // APNG input is written back as APNG
+    let mut png_data = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut png_data, 5, 5);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_animated(2, 0).unwrap();
+        let mut writer = encoder.write_header().unwrap();
+        for frame in animation_frames() {
+            writer.set_frame_delay(frame.delay().numer_denom_ms().0 as u16, 1000).unwrap();
+            writer.write_image_data(frame.buffer().as_raw()).unwrap();
+        }
+        writer.finish().unwrap();
+    }
+    
+    let result = nine_patch_animated(&png_data, &8u32.to_le_bytes(), &6u32.to_le_bytes(), &[]);
+    let decoder = PngDecoder::new(Cursor::new(&result)).unwrap();
+    assert!(decoder.is_apng().unwrap());
+    let frames = decoder.apng().unwrap().into_frames().collect_frames().unwrap();
+    assert_eq!(frames.len(), 2);
+    assert_eq!(*frames[1].buffer().get_pixel(7, 5), Rgba([0, 0, 255, 255]));
+    let (numerator, denominator) = frames[0].delay().numer_denom_ms();
+    assert_eq!(numerator / denominator, 100);
+    
+    // Frames with markers must agree with the first frame
+    let mut frames = animation_frames();
+    let mut moved: ImageBuffer<Rgba<u8>, Vec<u8>> = frames[1].buffer().clone();
+    moved.put_pixel(3, 0, Rgba([0, 0, 0, 255]));
+    frames[1] = Frame::new(moved);
+    
+    let mut gif_data = Vec::new();
+    {
+        let mut encoder = GifEncoder::new(&mut gif_data);
+        encoder.encode_frames(frames).unwrap();
+    }
+    assert!(nine_patch_animated(&gif_data, &8u32.to_le_bytes(), &6u32.to_le_bytes(), &[]).is_empty());
+}
+
+#[test]
+fn test_nine_patch_frame() {
+    let mut gif_data = Vec::new();
+    {
+        let mut encoder = GifEncoder::new(&mut gif_data);
+        encoder.encode_frames(animation_frames()).unwrap();
+    }
+    
+    // The frames last 100 and 200 milliseconds
+    let info: Value = ciborium::from_reader(animation_info(&gif_data).as_slice()).unwrap();
+    assert_eq!(info, cbor!({ "frame-count" => 2, "delays" => [100, 200], "duration" => 300 }).unwrap());
+    
+    let frame_color = |selector: Value| -> Rgba<u8> {
+        let frame = encode_options(selector);
+        let result = nine_patch_frame(&gif_data, &8u32.to_le_bytes(), &6u32.to_le_bytes(), &frame, &[]);
+        let result_img = image::load_from_memory(&result).unwrap().to_rgba8();
+        assert_eq!(result_img.dimensions(), (8, 6));
+        *result_img.get_pixel(4, 3)
+    };
+    
+    let red = Rgba([255, 0, 0, 255]);
+    let blue = Rgba([0, 0, 255, 255]);
+    assert_eq!(frame_color(cbor!({ "index" => 1 }).unwrap()), blue);
+    assert_eq!(frame_color(cbor!({ "time" => 99 }).unwrap()), red);
+    assert_eq!(frame_color(cbor!({ "time" => 100 }).unwrap()), blue);
+    
+    // Times wrap around at the end of the animation
+    assert_eq!(frame_color(cbor!({ "time" => 350 }).unwrap()), red);
+    
+    // Fractional sizes cover part of the last column, in still frames and
+    // whole animations
+    let frame = encode_options(cbor!({ "index" => 1 }).unwrap());
+    let result = nine_patch_frame_fractional(&gif_data, &7.5f32.to_le_bytes(), &6f32.to_le_bytes(), &frame, &[]);
+    let result_img = image::load_from_memory(&result).unwrap().to_rgba8();
+    assert_eq!(result_img.dimensions(), (8, 6));
+    assert_eq!(*result_img.get_pixel(4, 3), blue);
+    assert_eq!(result_img.get_pixel(7, 3)[3], 128);
+    
+    let result = nine_patch_animated_fractional(&gif_data, &7.5f32.to_le_bytes(), &6f32.to_le_bytes(), &[]);
+    let frames = GifDecoder::new(Cursor::new(&result)).unwrap().into_frames().collect_frames().unwrap();
+    assert_eq!(frames.len(), 2);
+    assert_eq!(frames[0].buffer().dimensions(), (8, 6));
+    assert!(nine_patch_animated_fractional(&gif_data, &f32::NAN.to_le_bytes(), &6f32.to_le_bytes(), &[]).is_empty());
+    
+    let frame = encode_options(cbor!({ "index" => 2 }).unwrap());
+    assert!(nine_patch_frame(&gif_data, &8u32.to_le_bytes(), &6u32.to_le_bytes(), &frame, &[]).is_empty());
+    
+    // Only the first and the requested frame are loaded, so a later frame with
+    // other markers fails only when it is shown
+    let mut frames = animation_frames();
+    let mut moved: ImageBuffer<Rgba<u8>, Vec<u8>> = frames[0].buffer().clone();
+    moved.put_pixel(3, 0, Rgba([0, 0, 0, 255]));
+    frames.push(Frame::new(moved));
+    let mut gif_data = Vec::new();
+    {
+        let mut encoder = GifEncoder::new(&mut gif_data);
+        encoder.encode_frames(frames).unwrap();
+    }
+    let frame = encode_options(cbor!({ "index" => 1 }).unwrap());
+    assert!(!nine_patch_frame(&gif_data, &8u32.to_le_bytes(), &6u32.to_le_bytes(), &frame, &[]).is_empty());
+    let frame = encode_options(cbor!({ "index" => 2 }).unwrap());
+    assert!(nine_patch_frame(&gif_data, &8u32.to_le_bytes(), &6u32.to_le_bytes(), &frame, &[]).is_empty());
+    
+    // The longest APNG delays add up to more than a u32 of milliseconds
+    let mut png_data = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut png_data, 3, 3);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_animated(70, 0).unwrap();
+        let mut writer = encoder.write_header().unwrap();
+        for _ in 0..70 {
+            writer.set_frame_delay(u16::MAX, 1).unwrap();
+            writer.write_image_data(&[0; 3 * 3 * 4]).unwrap();
+        }
+        writer.finish().unwrap();
+    }
+    
+    let info: Value = ciborium::from_reader(animation_info(&png_data).as_slice()).unwrap();
+    let duration = info.as_map().unwrap().iter().find(|(key, _)| key.as_text() == Some("duration")).unwrap().1.clone();
+    assert_eq!(duration, Value::from(70 * 65_535_000u64));
+}
+
+#[test]
+fn test_render_cache() {
+    // A 5x5 nine-patch whose content is red on the left and blue on the right
+    let mut img: ImageBuffer<Rgba<u8>, Vec<u8>> = ImageBuffer::from_pixel(5, 5, Rgba([0, 0, 0, 0]));
+    img.put_pixel(2, 0, Rgba([0, 0, 0, 255]));
+    img.put_pixel(0, 2, Rgba([0, 0, 0, 255]));
+    for y in 1..4 {
+        for x in 1..4 {
+            let color = if x < 3 { Rgba([255, 0, 0, 255]) } else { Rgba([0, 0, 255, 255]) };
+            img.put_pixel(x, y, color);
+        }
+    }
+    
+    let mut png_data = Vec::new();
+    img.write_to(&mut Cursor::new(&mut png_data), ImageFormat::Png).unwrap();
+    
+    let render = |options: &[u8]| nine_patch_with_options(&png_data, &12u32.to_le_bytes(), &9u32.to_le_bytes(), options);
+    
+    let plain = render(&[]);
+    assert!(!plain.is_empty());
+    assert_eq!(render(&[]), plain, "Cached render should match the first render");
+    
+    // Options that change the parsed patch must not reuse the cached one
+    let flipped = render(&encode_options(cbor!({ "flip-horizontal" => true }).unwrap()));
+    let flipped_img = image::load_from_memory(&flipped).unwrap().to_rgba8();
+    assert_eq!(*flipped_img.get_pixel(0, 4), Rgba([0, 0, 255, 255]));
+    assert_ne!(flipped, plain);
+    
+    // With the caches turned off every call renders again, with the same result
+    configure_cache(&encode_options(cbor!({ "asset-bytes" => 0, "render-bytes" => 0 }).unwrap()));
+    assert_eq!(render(&[]), plain);
+    configure_cache(&[]);
+    assert_eq!(render(&[]), plain);
+    
+    // Errors are not cached
+    assert!(nine_patch_with_options(&png_data[..10], &12u32.to_le_bytes(), &9u32.to_le_bytes(), &[]).is_empty());
+    assert!(nine_patch_with_options(&png_data[..10], &12u32.to_le_bytes(), &9u32.to_le_bytes(), &[]).is_empty());
+}
+
+#[test]
+fn test_handles() {
+    let mut img: ImageBuffer<Rgba<u8>, Vec<u8>> = ImageBuffer::from_pixel(6, 6, Rgba([0, 0, 0, 0]));
+    img.put_pixel(2, 0, Rgba([0, 0, 0, 255]));
+    img.put_pixel(0, 2, Rgba([0, 0, 0, 255]));
+    img.put_pixel(5, 2, Rgba([0, 0, 0, 255]));
+    img.put_pixel(2, 5, Rgba([0, 0, 0, 255]));
+    for y in 1..5 {
+        for x in 1..5 {
+            img.put_pixel(x, y, Rgba([40, 120, 200, 255]));
+        }
+    }
+    
+    let mut png_data = Vec::new();
+    img.write_to(&mut Cursor::new(&mut png_data), ImageFormat::Png).unwrap();
+    
+    let handle = load(&png_data);
+    assert_eq!(handle.len(), 8, "Handles should be 8 bytes");
+    assert_eq!(load(&png_data), handle, "Loading the same image should give the same handle");
+    
+    let width = 10.5f32.to_le_bytes();
+    let height = 7.0f32.to_le_bytes();
+    let options = encode_options(cbor!({ "rotate" => 90 }).unwrap());
+    
+    // Handles render exactly like the image bytes
+    let rendered = render_handle(&handle, &width, &height, &options);
+    assert!(!rendered.is_empty());
+    assert_eq!(rendered, nine_patch_fractional(&png_data, &width, &height, &options));
+    assert_eq!(info_handle(&handle, &options), nine_patch_content_info_with_options(&png_data, &options));
+    
+    // Malformed and unknown handles are errors
+    assert!(render_handle(&handle[..4], &width, &height, &[]).is_empty());
+    assert!(info_handle(&[0; 8], &[]).is_empty());
+    
+    // Turning the caches off evicts the loaded image
+    configure_cache(&encode_options(cbor!({ "asset-bytes" => 0, "render-bytes" => 0 }).unwrap()));
+    assert!(render_handle(&handle, &width, &height, &options).is_empty());
+    assert!(info_handle(&handle, &options).is_empty());
+    configure_cache(&[]);
+    
+    assert_eq!(load(&png_data), handle);
+    assert_eq!(render_handle(&handle, &width, &height, &options), rendered);
+}
+
+#[test]
+fn test_nine_patch_batch() {
+    let png_data = marked_png(5, 5);
+    
+    let tint = cbor!({ "tint" => "#ff0000" }).unwrap();
+    let requests = encode_options(cbor!([
+        [10, 8],
+        [12.5, 6, tint.clone()],
+        [10, 8],
+        [20, 4, { "rotate" => 90 }],
+    ]).unwrap());
+    
+    let result = nine_patch_batch(&png_data, &requests);
+    let images: Vec<Value> = ciborium::from_reader(result.as_slice()).unwrap();
+    assert_eq!(images.len(), 4, "Every request should have an image");
+    
+    let images: Vec<Vec<u8>> = images.into_iter().map(|image| image.into_bytes().unwrap()).collect();
+    
+    // Each image matches a single call with the same arguments
+    let single = |width: f32, height: f32, options: &[u8]| {
+        nine_patch_fractional(&png_data, &width.to_le_bytes(), &height.to_le_bytes(), options)
+    };
+    assert_eq!(images[0], single(10.0, 8.0, &[]));
+    assert_eq!(images[1], single(12.5, 6.0, &encode_options(tint)));
+    assert_eq!(images[2], images[0]);
+    assert_eq!(images[3], single(20.0, 4.0, &encode_options(cbor!({ "rotate" => 90 }).unwrap())));
+    
+    let red = image::load_from_memory(&images[1]).unwrap().to_rgba8();
+    assert_eq!(red.dimensions(), (13, 6));
+    assert_eq!(*red.get_pixel(6, 3), Rgba([255, 0, 0, 255]));
+    
+    // A failing request fails the batch
+    let requests = encode_options(cbor!([[10, 8], [1, 1]]).unwrap());
+    assert!(nine_patch_batch(&png_data, &requests).is_empty());
+}
+
+#[test]
+fn test_resource_limits() {
+    let png_data = marked_png(5, 5);
+    
+    let render = |width: u32, height: u32| nine_patch(&png_data, &width.to_le_bytes(), &height.to_le_bytes());
+    
+    // A mistyped size fails instead of allocating gigabytes
+    assert!(render(400_000, 100).is_empty());
+    assert!(render(16_000, 16_000).is_empty());
+    assert!(!render(4_000, 100).is_empty());
+    
+    // Shadows count towards the output size
+    configure_limits(&encode_options(cbor!({ "max-output-width" => 100 }).unwrap()));
+    assert!(!render(100, 20).is_empty());
+    let shadow = encode_options(cbor!({ "shadow" => { "blur" => 4 } }).unwrap());
+    assert!(nine_patch_with_options(&png_data, &100u32.to_le_bytes(), &20u32.to_le_bytes(), &shadow).is_empty());
+    
+    // Inputs are checked before decoding, also when the image was cached before
+    configure_limits(&encode_options(cbor!({ "max-input-pixels" => 16 }).unwrap()));
+    assert!(render(20, 20).is_empty());
+    assert!(load(&png_data).is_empty());
+    
+    let mut gif_data = Vec::new();
+    {
+        let mut encoder = GifEncoder::new(&mut gif_data);
+        encoder.encode_frames(animation_frames()).unwrap();
+    }
+    assert!(nine_patch_animated(&gif_data, &8u32.to_le_bytes(), &6u32.to_le_bytes(), &[]).is_empty());
+    
+    configure_limits(&[]);
+    assert!(!render(20, 20).is_empty());
+    assert!(!nine_patch_animated(&gif_data, &8u32.to_le_bytes(), &6u32.to_le_bytes(), &[]).is_empty());
+}
+
+#[test]
+fn test_animation_output_limit() {
+    // 40 tiny frames pass the input limit together
+    let mut gif_data = Vec::new();
+    {
+        let mut encoder = GifEncoder::new(&mut gif_data);
+        encoder.encode_frames((0..20).flat_map(|_| animation_frames())).unwrap();
+    }
+    
+    // Each 1000x1000 frame is within the output limit, all 40 of them are not
+    assert!(nine_patch_animated(&gif_data, &1000u32.to_le_bytes(), &1000u32.to_le_bytes(), &[]).is_empty());
+    assert!(!nine_patch_animated(&gif_data, &8u32.to_le_bytes(), &6u32.to_le_bytes(), &[]).is_empty());
+    
+    // Shadows count towards the size of every frame
+    let shadow = encode_options(cbor!({ "shadow" => { "blur" => 300 } }).unwrap());
+    assert!(!nine_patch_animated(&gif_data, &200u32.to_le_bytes(), &200u32.to_le_bytes(), &[]).is_empty());
+    assert!(nine_patch_animated(&gif_data, &200u32.to_le_bytes(), &200u32.to_le_bytes(), &shadow).is_empty());
+}
+
+#[test]
+fn test_malformed_arguments() {
+    let png_data = marked_png(5, 5);
+    
+    let size = 10u32.to_le_bytes();
+    
+    // Sizes of other lengths are errors rather than panics
+    assert!(nine_patch(&png_data, &size[..2], &size).is_empty());
+    assert!(nine_patch_with_options(&png_data, &size, &[], &[]).is_empty());
+    assert!(nine_patch_fractional(&png_data, &[1, 2, 3, 4, 5], &size, &[]).is_empty());
+    
+    // Typst writes whole numbers as 8 bytes unless told otherwise
+    let result = nine_patch(&png_data, &10u64.to_le_bytes(), &size);
+    assert_eq!(image::load_from_memory(&result).unwrap().to_rgba8().dimensions(), (10, 10));
+    assert!(nine_patch(&png_data, &(1u64 << 32).to_le_bytes(), &size).is_empty());
+    
+    // Insets and pins that add up past the largest integer
+    let huge = u32::MAX;
+    let options = [
+        cbor!({ "border-width" => { "left" => 2, "top" => 2147483648u32, "right" => 0, "bottom" => 0 } }).unwrap(),
+        cbor!({ "outset" => { "left" => 1, "top" => huge, "right" => huge, "bottom" => 1 } }).unwrap(),
+        cbor!({ "pin" => [{ "column" => 1, "size" => huge }] }).unwrap(),
+        cbor!({ "shadow" => { "blur" => huge, "offset-x" => i32::MAX } }).unwrap(),
+    ];
+    for options in options {
+        let options = encode_options(options);
+        assert!(nine_patch_with_options(&png_data, &size, &size, &options).is_empty());
+        nine_patch_content_info_with_options(&png_data, &options);
+    }
+}
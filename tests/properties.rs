@@ -0,0 +1,217 @@
+use nine_patch::{nine_patch, nine_patch_content_info, nine_patch_with_options};
+use image::{ImageBuffer, ImageFormat, Rgba, RgbaImage};
+use proptest::collection::vec;
+use proptest::prelude::*;
+use ciborium::cbor;
+use std::io::Cursor;
+
+// A random nine-patch: the content pixels with the stretch and padding markers
+// that are drawn around them
+#[derive(Debug, Clone)]
+struct Patch {
+    content: RgbaImage,
+    stretch_columns: Vec<bool>,
+    stretch_rows: Vec<bool>,
+    // Padding as (left, top, right, bottom), without markers the whole content
+    padding: Option<(u32, u32, u32, u32)>,
+}
+
+impl Patch {
+    fn width(&self) -> u32 {
+        self.content.width()
+    }
+
+    fn height(&self) -> u32 {
+        self.content.height()
+    }
+
+    // Total size of the fixed columns and rows
+    fn min_size(&self) -> (u32, u32) {
+        let fixed = |stretch: &[bool]| stretch.iter().filter(|&&s| !s).count() as u32;
+        (fixed(&self.stretch_columns), fixed(&self.stretch_rows))
+    }
+
+    // Size of the fixed edges at either end of an axis
+    fn edges(stretch: &[bool]) -> (u32, u32) {
+        let first = stretch.iter().take_while(|&&s| !s).count() as u32;
+        let last = stretch.iter().rev().take_while(|&&s| !s).count() as u32;
+        (first, last)
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let (width, height) = (self.width(), self.height());
+        let black = Rgba([0, 0, 0, 255]);
+        let mut img = ImageBuffer::from_pixel(width + 2, height + 2, Rgba([0, 0, 0, 0]));
+
+        for (x, y, pixel) in self.content.enumerate_pixels() {
+            img.put_pixel(x + 1, y + 1, *pixel);
+        }
+        for (x, _) in self.stretch_columns.iter().enumerate().filter(|(_, &s)| s) {
+            img.put_pixel(x as u32 + 1, 0, black);
+        }
+        for (y, _) in self.stretch_rows.iter().enumerate().filter(|(_, &s)| s) {
+            img.put_pixel(0, y as u32 + 1, black);
+        }
+        if let Some((left, top, right, bottom)) = self.padding {
+            for x in left..width - right {
+                img.put_pixel(x + 1, height + 1, black);
+            }
+            for y in top..height - bottom {
+                img.put_pixel(width + 1, y + 1, black);
+            }
+        }
+
+        let mut png_data = Vec::new();
+        img.write_to(&mut Cursor::new(&mut png_data), ImageFormat::Png).unwrap();
+        png_data
+    }
+}
+
+// At least one stretch pixel on each axis
+fn stretch_markers(length: u32) -> impl Strategy<Value = Vec<bool>> {
+    vec(any::<bool>(), length as usize).prop_map(|mut markers| {
+        if !markers.contains(&true) {
+            let middle = markers.len() / 2;
+            markers[middle] = true;
+        }
+        markers
+    })
+}
+
+// A run of padding markers from `start` to `end`, as insets from both edges
+fn padding_insets(length: u32) -> impl Strategy<Value = (u32, u32)> {
+    (0..length, 0..length).prop_map(move |(a, b)| (a.min(b), length - a.max(b) - 1))
+}
+
+fn patch() -> impl Strategy<Value = Patch> {
+    (1u32..10, 1u32..10).prop_flat_map(|(width, height)| {
+        (
+            vec(any::<[u8; 4]>(), (width * height) as usize),
+            stretch_markers(width),
+            stretch_markers(height),
+            prop::option::of((padding_insets(width), padding_insets(height))),
+        )
+            .prop_map(move |(pixels, stretch_columns, stretch_rows, padding)| Patch {
+                content: ImageBuffer::from_fn(width, height, |x, y| Rgba(pixels[(y * width + x) as usize])),
+                stretch_columns,
+                stretch_rows,
+                padding: padding.map(|((left, right), (top, bottom))| (left, top, right, bottom)),
+            })
+    })
+}
+
+fn repeat_mode() -> impl Strategy<Value = &'static str> {
+    prop_oneof![Just("stretch"), Just("tile"), Just("tile-center"), Just("tile-fit"), Just("space")]
+}
+
+// Target sizes that fit the fixed columns and rows
+fn patch_and_target() -> impl Strategy<Value = (Patch, u32, u32)> {
+    (patch(), 0u32..24, 0u32..24).prop_map(|(patch, extra_width, extra_height)| {
+        let (min_width, min_height) = patch.min_size();
+        (patch, (min_width + extra_width).max(1), (min_height + extra_height).max(1))
+    })
+}
+
+fn render(patch: &Patch, width: u32, height: u32) -> RgbaImage {
+    let result = nine_patch(&patch.encode(), &width.to_le_bytes(), &height.to_le_bytes());
+    image::load_from_memory(&result).expect("Result should be a valid image").to_rgba8()
+}
+
+fn render_repeated(patch: &Patch, width: u32, height: u32, horizontal: &str, vertical: &str) -> RgbaImage {
+    let mut options = Vec::new();
+    let repeat = cbor!({ "repeat" => { "horizontal" => horizontal, "vertical" => vertical } }).unwrap();
+    ciborium::into_writer(&repeat, &mut options).unwrap();
+
+    let result = nine_patch_with_options(&patch.encode(), &width.to_le_bytes(), &height.to_le_bytes(), &options);
+    image::load_from_memory(&result).expect("Result should be a valid image").to_rgba8()
+}
+
+fn row(img: &RgbaImage, y: u32, columns: std::ops::Range<u32>) -> Vec<Rgba<u8>> {
+    columns.map(|x| *img.get_pixel(x, y)).collect()
+}
+
+fn column(img: &RgbaImage, x: u32, rows: std::ops::Range<u32>) -> Vec<Rgba<u8>> {
+    rows.map(|y| *img.get_pixel(x, y)).collect()
+}
+
+proptest! {
+    #[test]
+    fn output_size_matches_target(
+        (patch, width, height) in patch_and_target(),
+        horizontal in repeat_mode(),
+        vertical in repeat_mode(),
+    ) {
+        let result = render_repeated(&patch, width, height, horizontal, vertical);
+        prop_assert_eq!(result.dimensions(), (width, height));
+    }
+
+    #[test]
+    fn corners_match_source(
+        (patch, width, height) in patch_and_target(),
+        horizontal in repeat_mode(),
+        vertical in repeat_mode(),
+    ) {
+        let result = render_repeated(&patch, width, height, horizontal, vertical);
+        let (left, right) = Patch::edges(&patch.stretch_columns);
+        let (top, bottom) = Patch::edges(&patch.stretch_rows);
+
+        let corners = [
+            (0, 0, 0, 0, left, top),
+            (width - right, 0, patch.width() - right, 0, right, top),
+            (0, height - bottom, 0, patch.height() - bottom, left, bottom),
+            (width - right, height - bottom, patch.width() - right, patch.height() - bottom, right, bottom),
+        ];
+        for (dst_x, dst_y, src_x, src_y, corner_width, corner_height) in corners {
+            for y in 0..corner_height {
+                for x in 0..corner_width {
+                    prop_assert_eq!(
+                        result.get_pixel(dst_x + x, dst_y + y),
+                        patch.content.get_pixel(src_x + x, src_y + y),
+                        "corner pixel ({}, {})", dst_x + x, dst_y + y
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn natural_size_reproduces_content(patch in patch()) {
+        let result = render(&patch, patch.width(), patch.height());
+        prop_assert_eq!(result, patch.content);
+    }
+
+    // Every row of the output starts and ends with the fixed pixels of one
+    // source row, and every column likewise with those of one source column
+    #[test]
+    fn fixed_edges_keep_thickness((patch, width, height) in patch_and_target()) {
+        let result = render(&patch, width, height);
+        let (left, right) = Patch::edges(&patch.stretch_columns);
+        let (top, bottom) = Patch::edges(&patch.stretch_rows);
+        let content = &patch.content;
+
+        for y in 0..height {
+            let edges = (row(&result, y, 0..left), row(&result, y, width - right..width));
+            let matched = (0..patch.height()).any(|source| {
+                edges == (row(content, source, 0..left), row(content, source, patch.width() - right..patch.width()))
+            });
+            prop_assert!(matched, "row {} has no matching source row", y);
+        }
+        for x in 0..width {
+            let edges = (column(&result, x, 0..top), column(&result, x, height - bottom..height));
+            let matched = (0..patch.width()).any(|source| {
+                edges == (column(content, source, 0..top), column(content, source, patch.height() - bottom..patch.height()))
+            });
+            prop_assert!(matched, "column {} has no matching source column", x);
+        }
+    }
+
+    #[test]
+    fn content_info_round_trips(patch in patch()) {
+        let info = nine_patch_content_info(&patch.encode());
+        let values: Vec<u32> = info.chunks(4).map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]])).collect();
+
+        let (left, top, right, bottom) = patch.padding.unwrap_or((0, 0, 0, 0));
+        let (min_width, min_height) = patch.min_size();
+        prop_assert_eq!(values, vec![left, top, right, bottom, min_width, min_height]);
+    }
+}